@@ -0,0 +1,16 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use vquery::server::{
+    decode_challenge, decode_info_new, decode_info_new_lenient, decode_info_new_ref,
+    decode_info_old,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_challenge(data);
+    let _ = decode_info_old(data);
+    let _ = decode_info_new(data);
+    let _ = decode_info_new_lenient(data);
+    if let Ok(info) = decode_info_new_ref(data) {
+        info.to_owned();
+    }
+});
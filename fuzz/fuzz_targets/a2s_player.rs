@@ -0,0 +1,19 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use vquery::server::{
+    decode_players, decode_players_lenient, decode_players_ref, SessionTracker, THE_SHIP_APP_ID,
+};
+
+fuzz_target!(|data: &[u8]| {
+    for &app_id in &[0, THE_SHIP_APP_ID] {
+        if let Ok(list) = decode_players(data, app_id) {
+            let mut tracker = SessionTracker::new();
+            tracker.update(&list);
+            tracker.update(&list);
+        }
+        let _ = decode_players_lenient(data, app_id);
+        if let Ok(list) = decode_players_ref(data, app_id) {
+            list.to_owned();
+        }
+    }
+});
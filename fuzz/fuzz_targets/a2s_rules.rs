@@ -0,0 +1,16 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use vquery::server::{decode_rules, decode_rules_lenient, decode_rules_ref, Rules};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(list) = decode_rules(data) {
+        let rules = Rules::from(&list);
+        let _ = rules.time_limit();
+        let _ = rules.tf_game_modes();
+        let _ = rules.diff(&Rules::default());
+    }
+    let _ = decode_rules_lenient(data);
+    if let Ok(list) = decode_rules_ref(data) {
+        list.to_owned();
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use vquery::master::{decode_reply, parse_filters};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_reply(data);
+    if let Ok(wire) = std::str::from_utf8(data) {
+        let _ = parse_filters(wire);
+    }
+});
@@ -0,0 +1,7 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use vquery::{pcap::replay, server::SourceParser};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = replay::<SourceParser>(data, &[]);
+});
@@ -0,0 +1,18 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use vquery::server::{GoldsrcParser, PacketParser, Reassembler, SourceParser};
+
+/// Input is a sequence of datagrams, each prefixed with its length as u16.
+fn feed<P: PacketParser>(mut data: &[u8]) {
+    let mut reassembler = Reassembler::<P>::new();
+    while data.len() >= 2 {
+        let len = (u16::from_le_bytes([data[0], data[1]]) as usize).min(data.len() - 2);
+        let _ = reassembler.feed(&data[2..2 + len]);
+        data = &data[2 + len..];
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    feed::<SourceParser>(data);
+    feed::<GoldsrcParser>(data);
+});
@@ -0,0 +1,85 @@
+use pico_args::Arguments;
+use std::{
+    error::Error as StdError,
+    fs,
+    net::{SocketAddr, ToSocketAddrs},
+    time::Duration,
+};
+use vquery::exporter::{Exporter, Target};
+
+const HELP: &str = "\
+Prometheus exporter of Valve's goldsrc/source servers
+
+USAGE:
+    vquery-exporter [OPTIONS] [SERVER]...
+
+SERVER is `[goldsrc:|source:]host:port`, source is the default.
+
+OPTIONS:
+    -l, --listen <ADDRESS>    Address to serve /metrics on [default: 127.0.0.1:9150]
+    -i, --interval <SECS>     Interval between queries [default: 15]
+    -t, --timeout <SECS>      Timeout of every query [default: 5]
+    -c, --config <FILE>       File with servers, one per line
+    -h, --help                Print help
+";
+
+fn parse_target(line: &str) -> Result<Target, Box<dyn StdError>> {
+    let (goldsrc, address) = if let Some(address) = line.strip_prefix("goldsrc:") {
+        (true, address)
+    } else {
+        (false, line.strip_prefix("source:").unwrap_or(line))
+    };
+    let address: SocketAddr = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| format!("can't resolve `{}`", address))?;
+    Ok(Target { address, goldsrc })
+}
+
+fn main() -> Result<(), Box<dyn StdError>> {
+    let mut args = Arguments::from_env();
+    if args.contains(["-h", "--help"]) {
+        print!("{}", HELP);
+        return Ok(());
+    }
+    let listen = args
+        .opt_value_from_str(["-l", "--listen"])?
+        .unwrap_or_else(|| "127.0.0.1:9150".parse().unwrap());
+    let interval: f32 = args
+        .opt_value_from_str(["-i", "--interval"])?
+        .unwrap_or(15.0);
+    let timeout: f32 = args.opt_value_from_str(["-t", "--timeout"])?.unwrap_or(5.0);
+    let config: Option<String> = args.opt_value_from_str(["-c", "--config"])?;
+
+    let mut lines: Vec<String> = args
+        .finish()
+        .into_iter()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    if let Some(config) = config {
+        lines.extend(fs::read_to_string(config)?.lines().map(str::to_owned));
+    }
+    let targets = lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_target)
+        .collect::<Result<Vec<_>, _>>()?;
+    if targets.is_empty() {
+        return Err("no servers to export, see --help".into());
+    }
+
+    let secs = |name: &str, secs: f32| {
+        Duration::try_from_secs_f32(secs)
+            .ok()
+            .filter(|secs| !secs.is_zero())
+            .ok_or_else(|| format!("invalid {} `{}`", name, secs))
+    };
+    Exporter::new(
+        targets,
+        secs("interval", interval)?,
+        secs("timeout", timeout)?,
+    )
+    .serve(listen)?;
+    Ok(())
+}
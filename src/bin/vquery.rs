@@ -0,0 +1,488 @@
+use pico_args::Arguments;
+use serde_json::{json, Value};
+use std::{
+    error::Error as StdError,
+    ffi::CString,
+    net::{SocketAddr, SocketAddrV4, ToSocketAddrs},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+use vquery::{
+    master::{parse_filters, Region, ServersQuery},
+    pcap::{self, Reply},
+    server::{
+        Error as ServerError, GoldsrcParser, InfoNew, InfoOld, PacketParser, PlayersList, Rules,
+        SourceParser, ValveQuery,
+    },
+    ErrorKind,
+};
+
+type CliResult<T> = Result<T, Box<dyn StdError>>;
+
+const HELP: &str = "\
+Query Valve's goldsrc/source servers and master servers
+
+USAGE:
+    vquery [OPTIONS] <COMMAND> [ADDRESS]
+
+COMMANDS:
+    info       Print server's information
+    players    Print players on the server
+    rules      Print server's rules
+    ping       Measure round-trip time of information requests
+    master     List servers known to the master server
+    replay     Decode replies captured in a pcap/pcapng file at ADDRESS
+
+OPTIONS:
+    -e, --engine <ENGINE>    goldsrc, source or auto [default: auto]
+    -t, --timeout <SECS>     Timeout of every request [default: 5]
+    -f, --filter <FILTER>    Master server filter, e.g. \\appid\\730\\empty\\1
+    -r, --region <REGION>    Master server region, code or `all` [default: all]
+    -n, --count <COUNT>      Number of pings or servers to list
+        --json               Print JSON instead of tables
+    -h, --help               Print help
+";
+
+const DEFAULT_MASTER: &str = "hl2master.steampowered.com:27011";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Engine {
+    Goldsrc,
+    Source,
+    Auto,
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "goldsrc" => Ok(Engine::Goldsrc),
+            "source" => Ok(Engine::Source),
+            "auto" => Ok(Engine::Auto),
+            _ => Err(format!("unknown engine `{}`", s)),
+        }
+    }
+}
+
+enum Info {
+    New(InfoNew),
+    Old(InfoOld),
+}
+
+impl Info {
+    /// App ids of goldsrc games are below 200, e.g. 10 for Counter-Strike.
+    fn is_goldsrc(&self) -> bool {
+        match self {
+            Info::New(info) => (0..200).contains(&info.steamid),
+            Info::Old(_) => true,
+        }
+    }
+
+    fn app_id(&self) -> i16 {
+        match self {
+            Info::New(info) => info.steamid,
+            Info::Old(_) => 0,
+        }
+    }
+}
+
+struct Options {
+    json: bool,
+    timeout: Duration,
+    count: Option<usize>,
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> CliResult<()> {
+    let mut args = Arguments::from_env();
+    if args.contains(["-h", "--help"]) {
+        print!("{}", HELP);
+        return Ok(());
+    }
+
+    let engine = args
+        .opt_value_from_str(["-e", "--engine"])?
+        .unwrap_or(Engine::Auto);
+    let timeout: f32 = args.opt_value_from_str(["-t", "--timeout"])?.unwrap_or(5.0);
+    let options = Options {
+        json: args.contains("--json"),
+        timeout: Duration::try_from_secs_f32(timeout)
+            .ok()
+            .filter(|timeout| !timeout.is_zero())
+            .ok_or_else(|| format!("invalid timeout `{}`", timeout))?,
+        count: args.opt_value_from_str(["-n", "--count"])?,
+    };
+    let filter: Option<String> = args.opt_value_from_str(["-f", "--filter"])?;
+    let region: Option<String> = args.opt_value_from_str(["-r", "--region"])?;
+    let command: String = args
+        .free_from_str()
+        .map_err(|_| "missing command, see --help")?;
+    let address: Option<String> = args.opt_free_from_str()?;
+    let unused = args.finish();
+    if !unused.is_empty() {
+        return Err(format!("unexpected arguments: {:?}", unused).into());
+    }
+
+    if command == "master" {
+        let address = address.as_deref().unwrap_or(DEFAULT_MASTER);
+        return master(
+            resolve(address)?,
+            filter.as_deref().unwrap_or(""),
+            parse_region(region.as_deref().unwrap_or("all"))?,
+            &options,
+        );
+    }
+    if command == "replay" {
+        let path = address.ok_or("missing capture file")?;
+        return match engine {
+            Engine::Goldsrc => replay::<GoldsrcParser>(&path, &options),
+            _ => replay::<SourceParser>(&path, &options),
+        };
+    }
+    if !["info", "players", "rules", "ping"].contains(&command.as_str()) {
+        return Err(format!("unknown command `{}`, see --help", command).into());
+    }
+    let address = resolve(&address.ok_or("missing server address")?)?;
+    match engine {
+        Engine::Goldsrc => server::<GoldsrcParser>(&command, address, None, &options),
+        Engine::Source => server::<SourceParser>(&command, address, None, &options),
+        Engine::Auto => {
+            let info = query_info(&connect::<SourceParser>(address, options.timeout)?)?;
+            if info.is_goldsrc() {
+                server::<GoldsrcParser>(&command, address, Some(info), &options)
+            } else {
+                server::<SourceParser>(&command, address, Some(info), &options)
+            }
+        }
+    }
+}
+
+fn resolve(address: &str) -> CliResult<SocketAddr> {
+    let resolved = address
+        .to_socket_addrs()
+        .or_else(|_| (address, 27015).to_socket_addrs())?
+        .next();
+    Ok(resolved.ok_or_else(|| format!("can't resolve `{}`", address))?)
+}
+
+fn parse_region(region: &str) -> CliResult<Region> {
+    Ok(match region {
+        "all" => Region::All,
+        "0" => Region::UsEastCost,
+        "1" => Region::UsWestCost,
+        "2" => Region::SouthAmerica,
+        "3" => Region::Europe,
+        "4" => Region::Asia,
+        "5" => Region::Australia,
+        "6" => Region::MiddleEast,
+        "7" => Region::Africa,
+        _ => return Err(format!("unknown region `{}`", region).into()),
+    })
+}
+
+fn local_addr(remote: SocketAddr) -> SocketAddr {
+    match remote {
+        SocketAddr::V4(_) => "0.0.0.0:0".parse().unwrap(),
+        SocketAddr::V6(_) => "[::]:0".parse().unwrap(),
+    }
+}
+
+fn connect<P: PacketParser>(address: SocketAddr, timeout: Duration) -> CliResult<ValveQuery<P>> {
+    let query = ValveQuery::bind(local_addr(address))?;
+    query.set_timeout(Some(timeout))?;
+    query.connect(address)?;
+    Ok(query)
+}
+
+/// Falls back to the obsolete goldsrc reply if the server doesn't know the new one.
+fn query_info<P: PacketParser>(query: &ValveQuery<P>) -> CliResult<Info> {
+    match query.a2s_info_new() {
+        Ok(info) => Ok(Info::New(info)),
+        Err(ServerError::UnexpectedHeader { .. }) => Ok(Info::Old(query.a2s_info_old()?)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn server<P: PacketParser>(
+    command: &str,
+    address: SocketAddr,
+    info: Option<Info>,
+    options: &Options,
+) -> CliResult<()> {
+    let query = connect::<P>(address, options.timeout)?;
+    match command {
+        "info" => {
+            let info = match info {
+                Some(info) => info,
+                None => query_info(&query)?,
+            };
+            print_info(&info, options);
+        }
+        "players" => {
+            let app_id = info.map_or(0, |info| info.app_id());
+            let challenge = query.a2s_player_challenge()?;
+            print_players(&query.a2s_players_with_app_id(challenge, app_id)?, options);
+        }
+        "rules" => {
+            let challenge = query.a2s_rules_challenge()?;
+            print_rules(&Rules::from(query.a2s_rules(challenge)?), options);
+        }
+        "ping" => ping(&query, options)?,
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+fn master(address: SocketAddr, filter: &str, region: Region, options: &Options) -> CliResult<()> {
+    let filters = parse_filters(filter).ok_or_else(|| format!("invalid filter `{}`", filter))?;
+    let query = ServersQuery::bind(local_addr(address))?;
+    query.set_timeout(Some(options.timeout))?;
+    query.connect(address)?;
+
+    let mut servers = vec![];
+    for server in query
+        .iter(region, &filters)
+        .take(options.count.unwrap_or(usize::MAX))
+    {
+        servers.push(server?);
+    }
+    if options.json {
+        let servers: Vec<_> = servers.iter().map(SocketAddrV4::to_string).collect();
+        println!("{}", json!(servers));
+    } else {
+        servers.iter().for_each(|server| println!("{}", server));
+    }
+    Ok(())
+}
+
+fn replay<P: PacketParser>(path: &str, options: &Options) -> CliResult<()> {
+    let capture = std::fs::read(path)?;
+    for record in pcap::replay::<P>(&capture, &[])? {
+        let time = record.timestamp.as_secs_f64();
+        let (server, client) = (record.server.to_string(), record.client.to_string());
+        let summary = match &record.reply {
+            Ok(Reply::Challenge(challenge)) => format!("A2S_CHALLENGE {:#010x}", challenge),
+            Ok(Reply::InfoNew(info)) => format!(
+                "A2S_INFO {} on {}, {}/{} players",
+                string(&info.name),
+                string(&info.map),
+                info.players,
+                info.max_players
+            ),
+            Ok(Reply::InfoOld(info)) => format!(
+                "A2S_INFO {} on {}, {}/{} players",
+                string(&info.name),
+                string(&info.map),
+                info.players,
+                info.max_players
+            ),
+            Ok(Reply::Players(list)) => format!("A2S_PLAYER {} players", list.players.len()),
+            Ok(Reply::Rules(list)) => format!("A2S_RULES {} rules", list.rules.len()),
+            Ok(Reply::Master(servers)) => format!("master reply, {} servers", servers.len()),
+            Err(err) => format!("error: {}", err),
+        };
+        if options.json {
+            let ok = record.reply.is_ok();
+            println!(
+                "{}",
+                json!({ "time": time, "server": server, "client": client, "ok": ok, "reply": summary })
+            );
+        } else {
+            println!("{:.6} {} -> {}: {}", time, server, client, summary);
+        }
+    }
+    Ok(())
+}
+
+fn ping<P: PacketParser>(query: &ValveQuery<P>, options: &Options) -> CliResult<()> {
+    let mut times = vec![];
+    for _ in 0..options.count.unwrap_or(4) {
+        let start = Instant::now();
+        match query.a2s_info_new() {
+            Ok(_) => times.push(start.elapsed()),
+            Err(err) if is_timeout(&err) => {
+                if !options.json {
+                    println!("timeout");
+                }
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        }
+        if !options.json {
+            println!("{:.2} ms", millis(*times.last().unwrap()));
+        }
+    }
+
+    let lost = options.count.unwrap_or(4) - times.len();
+    if options.json {
+        let times: Vec<_> = times.iter().map(|&time| millis(time)).collect();
+        println!("{}", json!({ "times_ms": times, "lost": lost }));
+    } else if let (Some(min), Some(max)) = (times.iter().min(), times.iter().max()) {
+        let avg = times.iter().sum::<Duration>() / times.len() as u32;
+        println!(
+            "min/avg/max = {:.2}/{:.2}/{:.2} ms, lost {}",
+            millis(*min),
+            millis(avg),
+            millis(*max),
+            lost
+        );
+    }
+    Ok(())
+}
+
+fn is_timeout(err: &ServerError) -> bool {
+    err.kind() == ErrorKind::Timeout
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn string(s: &CString) -> String {
+    s.to_string_lossy().into_owned()
+}
+
+fn print_info(info: &Info, options: &Options) {
+    let fields: Vec<(&str, Value)> = match info {
+        Info::New(info) => {
+            let extra = &info.extra_data;
+            vec![
+                ("name", json!(string(&info.name))),
+                ("map", json!(string(&info.map))),
+                ("folder", json!(string(&info.folder))),
+                ("game", json!(string(&info.game))),
+                ("app_id", json!(info.steamid)),
+                ("players", json!(info.players)),
+                ("max_players", json!(info.max_players)),
+                ("bots", json!(info.bots)),
+                ("server_type", json!((info.server_type as char).to_string())),
+                ("environment", json!((info.enviroment as char).to_string())),
+                ("password", json!(info.is_visible)),
+                ("vac_secured", json!(info.vac_secured)),
+                ("version", json!(string(&info.version))),
+                ("port", json!(extra.port)),
+                ("steam_id", json!(extra.server_steamid)),
+                ("keywords", json!(extra.keywords.as_ref().map(string))),
+                ("game_id", json!(extra.gameid)),
+            ]
+        }
+        Info::Old(info) => vec![
+            ("address", json!(string(&info.address))),
+            ("name", json!(string(&info.name))),
+            ("map", json!(string(&info.map))),
+            ("folder", json!(string(&info.folder))),
+            ("game", json!(string(&info.game))),
+            ("players", json!(info.players)),
+            ("max_players", json!(info.max_players)),
+            ("bots", json!(info.bots_num)),
+            ("protocol", json!(info.protocol)),
+            ("server_type", json!((info.server_type as char).to_string())),
+            ("environment", json!((info.enviroment as char).to_string())),
+            ("password", json!(info.is_private)),
+            ("vac_secured", json!(info.vac_secured)),
+        ],
+    };
+
+    if options.json {
+        let object: serde_json::Map<_, _> = fields
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect();
+        println!("{}", Value::Object(object));
+    } else {
+        let rows = fields
+            .into_iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    Value::String(s) => s,
+                    Value::Null => String::from("-"),
+                    value => value.to_string(),
+                };
+                vec![key.to_owned(), value]
+            })
+            .collect();
+        print_table(&["Field", "Value"], rows);
+    }
+}
+
+fn print_players(list: &PlayersList, options: &Options) {
+    if options.json {
+        let players: Vec<_> = list
+            .players
+            .iter()
+            .map(|player| {
+                json!({
+                    "name": string(&player.name),
+                    "score": player.score,
+                    "duration_secs": player.duration.as_secs_f32(),
+                    "deaths": player.the_ship.as_ref().map(|ship| ship.deaths),
+                    "money": player.the_ship.as_ref().map(|ship| ship.money),
+                })
+            })
+            .collect();
+        println!("{}", json!(players));
+    } else {
+        let rows = list
+            .players
+            .iter()
+            .map(|player| {
+                let duration = player.duration.as_secs();
+                vec![
+                    string(&player.name),
+                    player.score.to_string(),
+                    format!(
+                        "{}:{:02}:{:02}",
+                        duration / 3600,
+                        duration / 60 % 60,
+                        duration % 60
+                    ),
+                ]
+            })
+            .collect();
+        print_table(&["Name", "Score", "Duration"], rows);
+    }
+}
+
+fn print_rules(rules: &Rules, options: &Options) {
+    let mut rules: Vec<_> = rules.iter().collect();
+    rules.sort_unstable();
+    if options.json {
+        let object: serde_json::Map<_, _> = rules
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), json!(value)))
+            .collect();
+        println!("{}", Value::Object(object));
+    } else {
+        let rows = rules
+            .into_iter()
+            .map(|(key, value)| vec![key.to_owned(), value.to_owned()])
+            .collect();
+        print_table(&["Rule", "Value"], rows);
+    }
+}
+
+fn print_table(header: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<_> = header.iter().map(|column| column.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let print_row = |row: Vec<String>| {
+        let line: Vec<_> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{:width$}", cell, width = width))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    };
+    print_row(header.iter().map(|&column| column.to_owned()).collect());
+    rows.into_iter().for_each(print_row);
+}
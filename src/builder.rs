@@ -0,0 +1,215 @@
+use crate::{
+    master::ServersQuery,
+    server::{PacketParser, ValveQuery},
+    Limits, RateLimiter,
+};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    io::Result as IOResult,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    sync::Arc,
+    time::Duration,
+};
+
+/// Configures an UDP socket and turns it into `ValveQuery` or `ServersQuery`.
+///
+/// The socket is bound to `0.0.0.0:0` unless another local address is set,
+/// the rest of the options are left to the OS by default.
+#[derive(Debug, Clone)]
+pub struct QueryBuilder {
+    local_addr: SocketAddr,
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    interface: Option<String>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+    recv_buffer_size: Option<usize>,
+    send_buffer_size: Option<usize>,
+    reuse_address: bool,
+    ttl: Option<u32>,
+    dscp: Option<u8>,
+    packet_size: Option<usize>,
+    limits: Limits,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl Default for QueryBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QueryBuilder {
+    pub fn new() -> Self {
+        Self {
+            local_addr: (Ipv4Addr::UNSPECIFIED, 0).into(),
+            #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+            interface: None,
+            read_timeout: None,
+            write_timeout: None,
+            recv_buffer_size: None,
+            send_buffer_size: None,
+            reuse_address: false,
+            ttl: None,
+            dscp: None,
+            packet_size: None,
+            limits: Limits::default(),
+            limiter: None,
+        }
+    }
+
+    /// Source address of requests, e.g. `[::]:0` for IPv6 servers.
+    pub fn local_addr(mut self, addr: SocketAddr) -> Self {
+        self.local_addr = addr;
+        self
+    }
+
+    /// Sends requests through the network interface, e.g. `eth1` (`SO_BINDTODEVICE`).
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub fn interface(mut self, name: &str) -> Self {
+        self.interface = Some(name.into());
+        self
+    }
+
+    pub fn read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    pub fn write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    pub fn recv_buffer_size(mut self, size: usize) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    pub fn reuse_address(mut self, reuse: bool) -> Self {
+        self.reuse_address = reuse;
+        self
+    }
+
+    /// IP time to live, or hop limit for IPv6.
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Differentiated services code point of requests, from 0 to 63.
+    #[cfg(not(any(
+        target_os = "fuchsia",
+        target_os = "redox",
+        target_os = "solaris",
+        target_os = "illumos",
+        target_os = "haiku",
+    )))]
+    pub fn dscp(mut self, dscp: u8) -> Self {
+        assert!(dscp < 64, "dscp must fit into 6 bits");
+        self.dscp = Some(dscp);
+        self
+    }
+
+    /// Size of a buffer for the first datagram of a reply, see `ValveQuery::set_packet_size`.
+    pub fn packet_size(mut self, size: usize) -> Self {
+        self.packet_size = Some(size);
+        self
+    }
+
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn rate_limiter(mut self, limiter: Option<Arc<RateLimiter>>) -> Self {
+        self.limiter = limiter;
+        self
+    }
+
+    /// Creates and binds a socket with the configured options.
+    pub fn socket(&self) -> IOResult<UdpSocket> {
+        let ipv6 = self.local_addr.is_ipv6();
+        let socket = Socket::new(
+            Domain::for_address(self.local_addr),
+            Type::DGRAM,
+            Some(Protocol::UDP),
+        )?;
+        #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+        if let Some(interface) = &self.interface {
+            socket.bind_device(Some(interface.as_bytes()))?;
+        }
+        socket.set_read_timeout(self.read_timeout)?;
+        socket.set_write_timeout(self.write_timeout)?;
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        socket.set_reuse_address(self.reuse_address)?;
+        match (self.ttl, ipv6) {
+            (Some(ttl), false) => socket.set_ttl(ttl)?,
+            (Some(hops), true) => socket.set_unicast_hops_v6(hops)?,
+            (None, _) => {}
+        }
+        if let Some(dscp) = self.dscp {
+            set_dscp(&socket, ipv6, dscp)?;
+        }
+        socket.bind(&self.local_addr.into())?;
+        Ok(socket.into())
+    }
+
+    pub fn valve_query<P: PacketParser>(&self) -> IOResult<ValveQuery<P>> {
+        let mut query = ValveQuery::with_transport(self.socket()?);
+        query.set_limits(self.limits);
+        query.set_rate_limiter(self.limiter.clone());
+        if let Some(size) = self.packet_size {
+            query.set_packet_size(size);
+        }
+        Ok(query)
+    }
+
+    pub fn servers_query(&self) -> IOResult<ServersQuery> {
+        let mut query = ServersQuery::with_transport(self.socket()?);
+        query.set_limits(self.limits);
+        query.set_rate_limiter(self.limiter.clone());
+        Ok(query)
+    }
+}
+
+/// DSCP takes the upper 6 bits of the TOS byte or the IPv6 traffic class.
+fn set_dscp(socket: &Socket, ipv6: bool, dscp: u8) -> IOResult<()> {
+    let tos = u32::from(dscp) << 2;
+    #[cfg(any(
+        target_os = "android",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "fuchsia",
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    if ipv6 {
+        return socket.set_tclass_v6(tos);
+    }
+    #[cfg(not(any(
+        target_os = "fuchsia",
+        target_os = "redox",
+        target_os = "solaris",
+        target_os = "illumos",
+        target_os = "haiku",
+    )))]
+    if !ipv6 {
+        return socket.set_tos(tos);
+    }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "DSCP isn't supported for this socket",
+    ))
+}
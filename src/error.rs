@@ -0,0 +1,76 @@
+use crate::{master, server};
+#[cfg(feature = "std")]
+use std::io::{Error as IOError, ErrorKind as IOErrorKind};
+use thiserror::Error;
+
+/// Coarse classification of errors, independent of the module which produced them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// No reply arrived before the socket's timeout.
+    Timeout,
+    /// Any other socket error.
+    Network,
+    /// Reply doesn't follow the protocol.
+    MalformedReply,
+    /// Server sent a challenge, so the request must be repeated with it.
+    ChallengeRequired,
+    /// Fragments of different multi-packet replies were mixed up.
+    Interrupted,
+    /// Compressed reply couldn't be decompressed.
+    Decompression,
+    /// Decompressed reply doesn't match its crc32.
+    Checksum,
+    /// Reply is larger than allowed by `Limits`.
+    LimitExceeded,
+}
+
+impl ErrorKind {
+    #[cfg(feature = "std")]
+    pub(crate) fn from_io(error: &IOError) -> Self {
+        match error.kind() {
+            IOErrorKind::WouldBlock | IOErrorKind::TimedOut => ErrorKind::Timeout,
+            _ => ErrorKind::Network,
+        }
+    }
+
+    /// Whether repeating the same request may succeed.
+    pub fn is_retryable(self) -> bool {
+        matches!(
+            self,
+            ErrorKind::Timeout
+                | ErrorKind::ChallengeRequired
+                | ErrorKind::Interrupted
+                | ErrorKind::Checksum
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Master(#[from] master::Error),
+    #[error(transparent)]
+    Server(#[from] server::Error),
+}
+
+impl From<server::PacketError> for Error {
+    fn from(error: server::PacketError) -> Self {
+        Error::Server(error.into())
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Master(err) => err.kind(),
+            Error::Server(err) => err.kind(),
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.kind().is_retryable()
+    }
+}
+
+pub type QueryResult<T> = Result<T, Error>;
@@ -0,0 +1,322 @@
+//! Prometheus exporter of servers' health.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use vquery::exporter::{Exporter, Target};
+//!
+//! let exporter = Exporter::new(
+//!     vec![Target::source("74.91.121.18:27015".parse().unwrap())],
+//!     Duration::from_secs(15),
+//!     Duration::from_secs(5),
+//! );
+//! exporter.serve("127.0.0.1:9150".parse().unwrap()).unwrap();
+//! ```
+use crate::server::{
+    Error, GoldsrcParser, InfoNew, InfoOld, PacketError, PacketParser, QueryResult, SourceParser,
+    ValveQuery,
+};
+use std::{
+    collections::HashMap,
+    fmt::Write as FmtWrite,
+    io::{BufRead, BufReader, Read, Result as IOResult, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+const MAX_REQUEST_LINE: u64 = 8 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub address: SocketAddr,
+    pub goldsrc: bool,
+}
+
+impl Target {
+    pub fn source(address: SocketAddr) -> Self {
+        Self {
+            address,
+            goldsrc: false,
+        }
+    }
+
+    pub fn goldsrc(address: SocketAddr) -> Self {
+        Self {
+            address,
+            goldsrc: true,
+        }
+    }
+}
+
+/// Part of `InfoNew`/`InfoOld` which is exported.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    players: u8,
+    max_players: u8,
+    bots: u8,
+    map: String,
+    vac_secured: bool,
+}
+
+impl From<InfoNew> for Snapshot {
+    fn from(info: InfoNew) -> Self {
+        Self {
+            players: info.players,
+            max_players: info.max_players,
+            bots: info.bots,
+            map: info.map.to_string_lossy().into_owned(),
+            vac_secured: info.vac_secured,
+        }
+    }
+}
+
+impl From<InfoOld> for Snapshot {
+    fn from(info: InfoOld) -> Self {
+        Self {
+            players: info.players,
+            max_players: info.max_players,
+            bots: info.bots_num,
+            map: info.map.to_string_lossy().into_owned(),
+            vac_secured: info.vac_secured,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Metrics {
+    snapshot: Option<Snapshot>,
+    latency: Option<Duration>,
+    queries: u64,
+    failures: u64,
+}
+
+pub struct Exporter {
+    targets: Vec<Target>,
+    interval: Duration,
+    timeout: Duration,
+    scrape_timeout: Duration,
+    metrics: Mutex<HashMap<SocketAddr, Metrics>>,
+}
+
+impl Exporter {
+    pub fn new(targets: Vec<Target>, interval: Duration, timeout: Duration) -> Self {
+        Self {
+            targets,
+            interval,
+            timeout,
+            scrape_timeout: Duration::from_secs(5),
+            metrics: Default::default(),
+        }
+    }
+
+    /// Read and write timeout of scrape connections, which are served one by one.
+    pub fn set_scrape_timeout(&mut self, timeout: Duration) {
+        self.scrape_timeout = timeout;
+    }
+
+    /// Queries every target once, in parallel.
+    pub fn poll(&self) {
+        let handles: Vec<_> = self
+            .targets
+            .iter()
+            .cloned()
+            .map(|target| {
+                let timeout = self.timeout;
+                thread::spawn(move || {
+                    let start = Instant::now();
+                    let snapshot = if target.goldsrc {
+                        query::<GoldsrcParser>(target.address, timeout)
+                    } else {
+                        query::<SourceParser>(target.address, timeout)
+                    };
+                    (target.address, snapshot.ok(), start.elapsed())
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            let (address, snapshot, elapsed) = handle.join().expect("query thread panicked");
+            let mut metrics = self.metrics.lock().unwrap();
+            let metrics = metrics.entry(address).or_default();
+            metrics.queries += 1;
+            if snapshot.is_some() {
+                metrics.latency = Some(elapsed);
+            } else {
+                metrics.failures += 1;
+                metrics.latency = None;
+            }
+            metrics.snapshot = snapshot;
+        }
+    }
+
+    /// Metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let metrics = self.metrics.lock().unwrap();
+        let states: Vec<_> = self
+            .targets
+            .iter()
+            .filter_map(|target| Some((target.address, metrics.get(&target.address)?)))
+            .collect();
+        let sample = |f: &dyn Fn(&Metrics) -> Option<(String, f64)>| -> Vec<Sample> {
+            states
+                .iter()
+                .filter_map(|(address, metrics)| {
+                    f(metrics).map(|(labels, value)| (*address, labels, value))
+                })
+                .collect()
+        };
+        let info = |f: fn(&Snapshot) -> f64| {
+            sample(&|metrics| metrics.snapshot.as_ref().map(|s| (String::new(), f(s))))
+        };
+
+        let mut out = String::new();
+        write_family(
+            &mut out,
+            ("vquery_up", "gauge", "Whether the last query succeeded."),
+            sample(&|m| Some((String::new(), m.snapshot.is_some() as u8 as f64))),
+        );
+        write_family(
+            &mut out,
+            (
+                "vquery_players",
+                "gauge",
+                "Number of players on the server.",
+            ),
+            info(|s| s.players as f64),
+        );
+        write_family(
+            &mut out,
+            (
+                "vquery_max_players",
+                "gauge",
+                "Maximum number of players on the server.",
+            ),
+            info(|s| s.max_players as f64),
+        );
+        write_family(
+            &mut out,
+            ("vquery_bots", "gauge", "Number of bots on the server."),
+            info(|s| s.bots as f64),
+        );
+        write_family(
+            &mut out,
+            (
+                "vquery_vac_secured",
+                "gauge",
+                "Whether the server is secured by VAC.",
+            ),
+            info(|s| s.vac_secured as u8 as f64),
+        );
+        write_family(
+            &mut out,
+            ("vquery_map_info", "gauge", "Current map of the server."),
+            sample(&|m| {
+                let map = &m.snapshot.as_ref()?.map;
+                Some((format!(",map=\"{}\"", escape(map)), 1.0))
+            }),
+        );
+        write_family(
+            &mut out,
+            (
+                "vquery_query_duration_seconds",
+                "gauge",
+                "Duration of the last successful query.",
+            ),
+            sample(&|m| Some((String::new(), m.latency?.as_secs_f64()))),
+        );
+        write_family(
+            &mut out,
+            (
+                "vquery_queries_total",
+                "counter",
+                "Number of queries sent to the server.",
+            ),
+            sample(&|m| Some((String::new(), m.queries as f64))),
+        );
+        write_family(
+            &mut out,
+            (
+                "vquery_query_failures_total",
+                "counter",
+                "Number of failed queries.",
+            ),
+            sample(&|m| Some((String::new(), m.failures as f64))),
+        );
+        out
+    }
+
+    /// Polls targets in background and serves `/metrics` on `address` forever.
+    pub fn serve(self, address: SocketAddr) -> IOResult<()> {
+        let listener = TcpListener::bind(address)?;
+        let exporter = Arc::new(self);
+        let poller = Arc::clone(&exporter);
+        thread::spawn(move || loop {
+            let start = Instant::now();
+            poller.poll();
+            thread::sleep(poller.interval.saturating_sub(start.elapsed()));
+        });
+
+        for stream in listener.incoming() {
+            // Broken scrape connections mustn't stop the exporter
+            let _ = stream.and_then(|stream| exporter.respond(stream));
+        }
+        Ok(())
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> IOResult<()> {
+        // A stalled client mustn't hold up the scrapes after it
+        stream.set_read_timeout(Some(self.scrape_timeout))?;
+        stream.set_write_timeout(Some(self.scrape_timeout))?;
+        let mut request_line = String::new();
+        BufReader::new(&stream)
+            .take(MAX_REQUEST_LINE)
+            .read_line(&mut request_line)?;
+        let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+        let (status, body) = if path == "/metrics" {
+            ("200 OK", self.render())
+        } else {
+            ("404 Not Found", String::from("Metrics are at /metrics\n"))
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+}
+
+fn query<P: PacketParser>(address: SocketAddr, timeout: Duration) -> QueryResult<Snapshot> {
+    let query = ValveQuery::<P>::connected(address, timeout).map_err(PacketError::from)?;
+    match query.a2s_info_new() {
+        Ok(info) => Ok(info.into()),
+        // Obsolete goldsrc servers reply with another header
+        Err(Error::UnexpectedHeader { .. }) => Ok(query.a2s_info_old()?.into()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Server's address, extra labels and value.
+type Sample = (SocketAddr, String, f64);
+
+fn write_family(out: &mut String, (name, kind, help): (&str, &str, &str), samples: Vec<Sample>) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+    for (address, labels, value) in samples {
+        writeln!(
+            out,
+            "{}{{server=\"{}\"{}}} {}",
+            name, address, labels, value
+        )
+        .unwrap();
+    }
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
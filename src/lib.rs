@@ -1,2 +1,38 @@
+//! Without the default `std` feature only the wire format is available: requests, parsers
+//! of replies and `Reassembler`, which can't decompress replies then.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub mod master;
 pub mod server;
+
+#[cfg(feature = "exporter")]
+pub mod exporter;
+
+#[cfg(feature = "pcap")]
+pub mod pcap;
+
+mod error;
+pub use error::*;
+
+#[cfg(feature = "std")]
+mod transport;
+#[cfg(feature = "std")]
+pub use transport::*;
+
+#[cfg(feature = "std")]
+mod ratelimit;
+#[cfg(feature = "std")]
+pub use ratelimit::*;
+
+#[cfg(feature = "std")]
+mod builder;
+#[cfg(feature = "std")]
+pub use builder::*;
+
+mod limits;
+pub use limits::*;
+
+mod parse;
+pub use parse::{FieldError, FieldResult, ParseError};
@@ -0,0 +1,59 @@
+use thiserror::Error;
+
+/// Caps on resources spent on a single reply, so a hostile server can't make
+/// a query allocate without bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Number of packets of a multi-packet reply.
+    pub fragments: usize,
+    /// Size of a single received datagram.
+    pub fragment_size: usize,
+    /// Size of a reassembled reply before decompression.
+    pub payload: usize,
+    /// Size of a decompressed reply.
+    pub decompressed: usize,
+    /// Size of a single reply of a master server.
+    pub master_reply: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            fragments: 64,
+            fragment_size: 4096,
+            payload: 256 * 1024,
+            decompressed: 1024 * 1024,
+            master_reply: 4096,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitKind {
+    Fragments,
+    FragmentSize,
+    Payload,
+    Decompressed,
+    MasterReply,
+}
+
+#[derive(Debug, Error)]
+#[error("Reply exceeds the limit of {kind:?}: {actual} > {limit}")]
+pub struct LimitExceeded {
+    pub kind: LimitKind,
+    pub limit: usize,
+    pub actual: usize,
+}
+
+impl LimitExceeded {
+    pub(crate) fn check(kind: LimitKind, limit: usize, actual: usize) -> Result<(), Self> {
+        if actual > limit {
+            return Err(Self {
+                kind,
+                limit,
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+use super::Filter;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::net::{Ipv4Addr, SocketAddrV4};
+use thiserror::Error;
+
+/// Longest filter which fits into a single datagram of a request along with the longest seed,
+/// i.e. `255.255.255.255:65535`.
+pub const MAX_FILTER_LEN: usize = 1400 - 2 - 21 - 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum FilterError {
+    #[error("Value of `{0}` filter is empty")]
+    EmptyValue(&'static str),
+    /// Backslashes separate keys and values, so they can't be escaped in values.
+    #[error("Value of `{key}` filter contains a reserved character: {value:?}")]
+    ReservedChar { key: &'static str, value: String },
+    #[error("`{0}` filter has no nested filters")]
+    EmptyGroup(&'static str),
+    #[error("Filter is {0} bytes long, more than {MAX_FILTER_LEN}")]
+    TooLong(usize),
+}
+
+fn check_value(key: &'static str, value: &str, reserved: &[char]) -> Result<(), FilterError> {
+    if value.is_empty() {
+        return Err(FilterError::EmptyValue(key));
+    }
+    if value.contains(|c| c == '\\' || c == '\0' || reserved.contains(&c)) {
+        return Err(FilterError::ReservedChar {
+            key,
+            value: value.into(),
+        });
+    }
+    Ok(())
+}
+
+/// Checks that `filters` produce a well-formed wire string, which the master accepts.
+pub fn validate_filters(filters: &[Filter]) -> Result<(), FilterError> {
+    fn validate_group(key: &'static str, group: &[Filter]) -> Result<(), FilterError> {
+        if group.is_empty() {
+            return Err(FilterError::EmptyGroup(key));
+        }
+        group.iter().try_for_each(validate_one)
+    }
+
+    fn validate_one(filter: &Filter) -> Result<(), FilterError> {
+        match filter {
+            Filter::Nor(group) => validate_group("nor", group),
+            Filter::Nand(group) => validate_group("nand", group),
+            Filter::GameDir(dir) => check_value("gamedir", dir, &[]),
+            Filter::Map(map) => check_value("map", map, &[]),
+            Filter::Appid(appid) => check_value("appid", appid, &[]),
+            Filter::NotAppid(appid) => check_value("napp", appid, &[]),
+            Filter::GameType(tags) => check_value("gametype", tags, &[]),
+            Filter::GameDataAll(tags) => check_value("gamedata", tags, &[]),
+            Filter::GameDataAny(tags) => check_value("gamedataor", tags, &[]),
+            Filter::NameMatch(name) => check_value("name_match", name, &[]),
+            Filter::VersionMatch(version) => check_value("version_match", version, &[]),
+            _ => Ok(()),
+        }
+    }
+
+    filters.iter().try_for_each(validate_one)?;
+    let len = filters.iter().map(|f| f.to_string().len()).sum();
+    if len > MAX_FILTER_LEN {
+        return Err(FilterError::TooLong(len));
+    }
+    Ok(())
+}
+
+/// Typed construction of filters, which are validated by `build`.
+///
+/// ```
+/// use vquery::master::FilterBuilder;
+///
+/// let filter = FilterBuilder::new()
+///     .app_id(730)
+///     .not_empty()
+///     .game_type(&["secure", "valve_ds"])
+///     .nor(FilterBuilder::new().map("de_dust2").linux())
+///     .to_wire()
+///     .unwrap();
+/// assert_eq!(
+///     filter,
+///     r"\appid\730\empty\1\gametype\secure,valve_ds\nor\2\map\de_dust2\linux\1"
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FilterBuilder {
+    filters: Vec<Filter>,
+    // The first error is reported by `build`, so setters can be chained
+    error: Option<FilterError>,
+}
+
+impl FilterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+
+    fn push_tags(mut self, key: &'static str, tags: &[&str], filter: fn(String) -> Filter) -> Self {
+        if tags.is_empty() {
+            self.error.get_or_insert(FilterError::EmptyValue(key));
+        }
+        for tag in tags {
+            if let Err(err) = check_value(key, tag, &[',']) {
+                self.error.get_or_insert(err);
+            }
+        }
+        self.push(filter(tags.join(",")))
+    }
+
+    fn push_group(mut self, group: FilterBuilder, filter: fn(Vec<Filter>) -> Filter) -> Self {
+        if let Some(err) = group.error {
+            self.error.get_or_insert(err);
+        }
+        self.push(filter(group.filters))
+    }
+
+    pub fn dedicated(self) -> Self {
+        self.push(Filter::Dedicated)
+    }
+
+    pub fn secure(self) -> Self {
+        self.push(Filter::Secure)
+    }
+
+    pub fn linux(self) -> Self {
+        self.push(Filter::Linux)
+    }
+
+    pub fn no_password(self) -> Self {
+        self.push(Filter::NoPassword)
+    }
+
+    pub fn not_empty(self) -> Self {
+        self.push(Filter::NotEmpty)
+    }
+
+    pub fn not_full(self) -> Self {
+        self.push(Filter::NotFull)
+    }
+
+    pub fn proxy(self) -> Self {
+        self.push(Filter::Proxy)
+    }
+
+    pub fn no_players(self) -> Self {
+        self.push(Filter::NoPlayers)
+    }
+
+    pub fn whitelisted(self) -> Self {
+        self.push(Filter::Whitelisted)
+    }
+
+    /// Only one server per unique IP address.
+    pub fn collapse_addr_hash(self) -> Self {
+        self.push(Filter::CollapseAddrHash)
+    }
+
+    pub fn app_id(self, app_id: u32) -> Self {
+        self.push(Filter::Appid(app_id.to_string()))
+    }
+
+    pub fn not_app_id(self, app_id: u32) -> Self {
+        self.push(Filter::NotAppid(app_id.to_string()))
+    }
+
+    pub fn game_dir(self, dir: &str) -> Self {
+        self.push(Filter::GameDir(dir.into()))
+    }
+
+    pub fn map(self, map: &str) -> Self {
+        self.push(Filter::Map(map.into()))
+    }
+
+    /// Hostname, which may contain `*` wildcards.
+    pub fn name_match(self, name: &str) -> Self {
+        self.push(Filter::NameMatch(name.into()))
+    }
+
+    /// Version, which may contain `*` wildcards.
+    pub fn version_match(self, version: &str) -> Self {
+        self.push(Filter::VersionMatch(version.into()))
+    }
+
+    /// Servers with all of `tags` in `sv_tags`.
+    pub fn game_type(self, tags: &[&str]) -> Self {
+        self.push_tags("gametype", tags, Filter::GameType)
+    }
+
+    /// Servers with all of `tags` in hidden tags (L4D2).
+    pub fn game_data_all(self, tags: &[&str]) -> Self {
+        self.push_tags("gamedata", tags, Filter::GameDataAll)
+    }
+
+    /// Servers with any of `tags` in hidden tags (L4D2).
+    pub fn game_data_any(self, tags: &[&str]) -> Self {
+        self.push_tags("gamedataor", tags, Filter::GameDataAny)
+    }
+
+    /// Servers on the IP address with any port.
+    pub fn game_ip(self, ip: Ipv4Addr) -> Self {
+        self.push(Filter::GameIp(ip))
+    }
+
+    pub fn game_addr(self, addr: SocketAddrV4) -> Self {
+        self.push(Filter::GameAddr(addr.into()))
+    }
+
+    /// Servers which match none of `group`.
+    pub fn nor(self, group: FilterBuilder) -> Self {
+        self.push_group(group, Filter::Nor)
+    }
+
+    /// Servers which don't match all of `group` at once.
+    pub fn nand(self, group: FilterBuilder) -> Self {
+        self.push_group(group, Filter::Nand)
+    }
+
+    pub fn build(self) -> Result<Vec<Filter>, FilterError> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        validate_filters(&self.filters)?;
+        Ok(self.filters)
+    }
+
+    /// Filters in the wire format, e.g. for `vquery --filter`.
+    pub fn to_wire(self) -> Result<String, FilterError> {
+        Ok(self.build()?.iter().map(|f| f.to_string()).collect())
+    }
+}
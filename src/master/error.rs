@@ -1,19 +1,57 @@
+use crate::{ErrorKind, LimitExceeded, ParseError};
+use core::net::SocketAddrV4;
 use thiserror::Error;
 
-type NomError<'a> = nom::Err<nom::error::Error<&'a [u8]>>;
-type NomErrorOwned = nom::Err<nom::error::Error<Vec<u8>>>;
+/// Reason to stop a crawl of a master, which would never end otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum NotTerminated {
+    /// Page ends with a server, which was the seed of an earlier page.
+    #[error("page ends with {0}, which was a seed already")]
+    RepeatedSeed(SocketAddrV4),
+    #[error("no end of the list after {0} pages")]
+    PageLimit(usize),
+    /// Page has neither servers nor the end of the list.
+    #[error("page is empty, but doesn't end the list")]
+    EmptyPage,
+}
 
 #[derive(Debug, Error)]
 pub enum Error {
+    #[cfg(feature = "std")]
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
-    Parse(NomErrorOwned),
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Limit(#[from] LimitExceeded),
+    #[error("Master crawl didn't terminate: {0}")]
+    NotTerminated(#[from] NotTerminated),
+    #[cfg(feature = "webapi")]
+    #[error("Web API replied with HTTP status {0}")]
+    HttpStatus(u16),
+    #[cfg(feature = "webapi")]
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[cfg(feature = "webapi")]
+    #[error("Web API reply has no valid `{0}`")]
+    WebApiField(&'static str),
 }
 
-impl From<NomError<'_>> for Error {
-    fn from(error: NomError<'_>) -> Self {
-        Error::Parse(error.map(|e| nom::error::make_error(e.input.to_vec(), e.code)))
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(err) => ErrorKind::from_io(err),
+            Error::Parse(_) => ErrorKind::MalformedReply,
+            Error::Limit(_) => ErrorKind::LimitExceeded,
+            Error::NotTerminated(NotTerminated::RepeatedSeed(_))
+            | Error::NotTerminated(NotTerminated::EmptyPage) => ErrorKind::MalformedReply,
+            Error::NotTerminated(NotTerminated::PageLimit(_)) => ErrorKind::LimitExceeded,
+            #[cfg(feature = "webapi")]
+            Error::HttpStatus(_) => ErrorKind::Network,
+            #[cfg(feature = "webapi")]
+            Error::Json(_) | Error::WebApiField(_) => ErrorKind::MalformedReply,
+        }
     }
 }
 
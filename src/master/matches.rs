@@ -0,0 +1,188 @@
+//! Evaluation of filters against A2S_INFO replies, because masters filter on stale data.
+use super::Filter;
+use crate::server::{InfoNew, InfoOld};
+use core::{convert::TryFrom, net::SocketAddr};
+
+#[derive(Clone, Copy)]
+enum AppId {
+    /// From the game id.
+    Full(u32),
+    /// Lower 16 bits of the app id from `steamid`, if there's no game id.
+    Short(u16),
+}
+
+/// Fields of both replies which filters depend on, `None` if a reply lacks the field.
+struct Fields<'a> {
+    name: &'a [u8],
+    map: &'a [u8],
+    folder: &'a [u8],
+    version: Option<&'a [u8]>,
+    keywords: Option<&'a [u8]>,
+    app_id: Option<AppId>,
+    address: Option<SocketAddr>,
+    players: u8,
+    max_players: u8,
+    server_type: u8,
+    environment: u8,
+    private: bool,
+    secure: bool,
+}
+
+impl<'a> From<&'a InfoNew> for Fields<'a> {
+    fn from(info: &'a InfoNew) -> Self {
+        // Game id holds the app id in its lower 24 bits, which don't fit into `steamid`
+        let app_id = match info.extra_data.gameid {
+            Some(gameid) => AppId::Full((gameid & 0xFF_FFFF) as u32),
+            None => AppId::Short(info.steamid as u16),
+        };
+        Self {
+            name: info.name.to_bytes(),
+            map: info.map.to_bytes(),
+            folder: info.folder.to_bytes(),
+            version: Some(info.version.to_bytes()),
+            keywords: info.extra_data.keywords.as_deref().map(|k| k.to_bytes()),
+            app_id: Some(app_id),
+            address: None,
+            players: info.players,
+            max_players: info.max_players,
+            server_type: info.server_type,
+            environment: info.enviroment,
+            private: info.is_visible,
+            secure: info.vac_secured,
+        }
+    }
+}
+
+impl<'a> From<&'a InfoOld> for Fields<'a> {
+    fn from(info: &'a InfoOld) -> Self {
+        Self {
+            name: info.name.to_bytes(),
+            map: info.map.to_bytes(),
+            folder: info.folder.to_bytes(),
+            version: None,
+            keywords: None,
+            app_id: None,
+            address: core::str::from_utf8(info.address.to_bytes())
+                .ok()
+                .and_then(|address| address.parse().ok()),
+            players: info.players,
+            max_players: info.max_players,
+            server_type: info.server_type,
+            environment: info.enviroment,
+            private: info.is_private,
+            secure: info.vac_secured,
+        }
+    }
+}
+
+/// Case-insensitive match of `text` against `pattern`, where `*` matches any substring.
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text it's matched up to
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                backtrack = Some((p, t));
+            }
+            Some(c) if c.eq_ignore_ascii_case(&text[t]) => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Whether comma-separated `tags` are all in comma-separated `keywords`.
+fn has_tags(keywords: &[u8], tags: &str) -> bool {
+    let keywords = keywords.split(|&b| b == b',').map(<[u8]>::trim_ascii);
+    tags.split(',').map(str::trim).all(|tag| {
+        keywords
+            .clone()
+            .any(|keyword| keyword.eq_ignore_ascii_case(tag.as_bytes()))
+    })
+}
+
+/// Kleene's disjunction, which is unknown unless something is true or everything is false.
+fn any(results: impl Iterator<Item = Option<bool>>) -> Option<bool> {
+    let mut unknown = false;
+    for result in results {
+        match result {
+            Some(true) => return Some(true),
+            Some(false) => {}
+            None => unknown = true,
+        }
+    }
+    Some(false).filter(|_| !unknown)
+}
+
+/// Kleene's conjunction, which is unknown unless something is false or everything is true.
+fn all(results: impl Iterator<Item = Option<bool>>) -> Option<bool> {
+    any(results.map(|result| result.map(|r| !r))).map(|r| !r)
+}
+
+impl Filter {
+    /// Whether the server with `info` passes the filter.
+    ///
+    /// Filters which the reply can't tell, e.g. `Whitelisted`, are unknown and don't reject
+    /// a server. Unknown filters in `Nor` and `Nand` follow three-valued logic.
+    pub fn matches(&self, info: &InfoNew) -> bool {
+        self.eval(&Fields::from(info)).unwrap_or(true)
+    }
+
+    /// Same as `matches` for the obsolete reply of goldsrc servers, which has no app id,
+    /// version and tags.
+    pub fn matches_old(&self, info: &InfoOld) -> bool {
+        self.eval(&Fields::from(info)).unwrap_or(true)
+    }
+
+    fn eval(&self, fields: &Fields<'_>) -> Option<bool> {
+        let type_is = |ty: u8| fields.server_type.eq_ignore_ascii_case(&ty);
+        let app_id_is = |app_id: &str| {
+            let actual = fields.app_id?;
+            let app_id = match app_id.parse::<u32>() {
+                Ok(app_id) => app_id,
+                Err(_) => return Some(false),
+            };
+            match actual {
+                AppId::Full(actual) => Some(actual == app_id),
+                // Larger ids can't be told apart by their lower bits
+                AppId::Short(actual) => u16::try_from(app_id).ok().map(|id| id == actual),
+            }
+        };
+        Some(match self {
+            Filter::Nor(group) => return any(group.iter().map(|f| f.eval(fields))).map(|r| !r),
+            Filter::Nand(group) => return all(group.iter().map(|f| f.eval(fields))).map(|r| !r),
+            Filter::Dedicated => type_is(b'd'),
+            Filter::Proxy => type_is(b'p'),
+            Filter::Linux => fields.environment.eq_ignore_ascii_case(&b'l'),
+            Filter::Secure => fields.secure,
+            Filter::NoPassword => !fields.private,
+            Filter::NotEmpty => fields.players > 0,
+            Filter::NotFull => fields.players < fields.max_players,
+            Filter::NoPlayers => fields.players == 0,
+            Filter::GameDir(dir) => fields.folder.eq_ignore_ascii_case(dir.as_bytes()),
+            Filter::Map(map) => fields.map.eq_ignore_ascii_case(map.as_bytes()),
+            Filter::Appid(app_id) => app_id_is(app_id)?,
+            Filter::NotAppid(app_id) => !app_id_is(app_id)?,
+            Filter::GameType(tags) => has_tags(fields.keywords?, tags),
+            Filter::NameMatch(name) => wildcard_match(name.as_bytes(), fields.name),
+            Filter::VersionMatch(version) => wildcard_match(version.as_bytes(), fields.version?),
+            Filter::GameAddr(addr) => fields.address? == *addr,
+            Filter::GameIp(ip) => fields.address?.ip() == *ip,
+            // Collapsing applies to the whole list rather than to a single server
+            Filter::CollapseAddrHash => true,
+            Filter::Whitelisted | Filter::GameDataAll(_) | Filter::GameDataAny(_) => return None,
+        })
+    }
+}
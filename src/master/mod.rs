@@ -1,19 +1,38 @@
-use std::{
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
     fmt::{Display, Formatter, Result as FmtResult},
-    io::Result as IOResult,
     iter::Iterator,
-    net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket},
-    time::Duration,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
 };
 
+use crate::ParseError;
+
 mod reply;
 use reply::Reply;
 mod error;
-use error::QueryResult;
-
-const BUF_SIZE: usize = 1 << 20; // 1Mb
-
-#[derive(Copy, Clone)]
+pub use error::*;
+mod builder;
+pub use builder::*;
+mod matches;
+#[cfg(feature = "std")]
+mod query;
+#[cfg(feature = "std")]
+pub use query::*;
+#[cfg(feature = "std")]
+mod pool;
+#[cfg(feature = "std")]
+pub use pool::*;
+#[cfg(feature = "webapi")]
+mod webapi;
+#[cfg(feature = "webapi")]
+pub use webapi::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Region {
     UsEastCost = 0x00,
     UsWestCost = 0x01,
@@ -26,6 +45,7 @@ pub enum Region {
     All = 0xFF,
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum Filter {
     Nor(Vec<Self>),
     Nand(Vec<Self>),
@@ -49,6 +69,8 @@ pub enum Filter {
     VersionMatch(String),
     CollapseAddrHash,
     GameAddr(SocketAddr),
+    /// `gameaddr` without a port.
+    GameIp(Ipv4Addr),
 }
 
 impl Display for Filter {
@@ -76,128 +98,93 @@ impl Display for Filter {
             Filter::GameDataAny(gdata) => write!(f, "\\gamedataor\\{}", gdata),
             Filter::NameMatch(hostname) => write!(f, "\\name_match\\{}", hostname),
             Filter::VersionMatch(version) => write!(f, "\\version_match\\{}", version),
-            Filter::CollapseAddrHash => write!(f, "\\collaspse_addr_hash\\1"),
+            Filter::CollapseAddrHash => write!(f, "\\collapse_addr_hash\\1"),
             Filter::GameAddr(addr) => write!(f, "\\gameaddr\\{}", addr),
+            Filter::GameIp(ip) => write!(f, "\\gameaddr\\{}", ip),
         }
     }
 }
 
-pub struct ServersQuery(UdpSocket);
-
-impl ServersQuery {
-    pub fn bind(addr: SocketAddr) -> IOResult<Self> {
-        UdpSocket::bind(addr).map(Self)
-    }
-
-    pub fn connect(&self, addr: SocketAddr) -> IOResult<()> {
-        self.0.connect(addr)
-    }
-
-    pub fn timeout(&self) -> IOResult<Option<Duration>> {
-        self.0.read_timeout()
-    }
-
-    pub fn set_timeout(&self, timeout: Option<Duration>) -> IOResult<()> {
-        self.0.set_read_timeout(timeout)
-    }
-
-    fn raw_request<A: AsRef<[u8]>, F: AsRef<[u8]>>(
-        &self,
-        message_type: u8,
-        region_code: u8,
-        addr: A,
-        filter: F,
-    ) -> IOResult<Vec<u8>> {
-        let addr = addr.as_ref();
-        let filter = filter.as_ref();
-        let mut data = Vec::with_capacity(4 + addr.len() + filter.len());
-        data.push(message_type);
-        data.push(region_code);
-        data.extend(addr);
-        data.push(0);
-        data.extend(filter);
-        data.push(0);
-
-        self.0.send(&data)?;
-
-        let mut buf = vec![0; BUF_SIZE]; // preallocation of 1mb is enough I think
-        let size = self.0.recv(&mut buf)?;
-        buf.truncate(size);
-        Ok(buf)
+/// Deepest nesting of `nor` and `nand` groups accepted by `parse_filters`.
+const MAX_FILTER_DEPTH: usize = 8;
+
+/// Parses filters from the wire format, i.e. `\appid\730\empty\1`.
+pub fn parse_filters(wire: &str) -> Option<Vec<Filter>> {
+    fn parse_one<'a>(tokens: &mut impl Iterator<Item = &'a str>, depth: usize) -> Option<Filter> {
+        let key = tokens.next()?;
+        let value = tokens.next()?;
+        let flag = |filter: Filter| Some(filter).filter(|_| value == "1");
+        match key {
+            "nor" | "nand" => {
+                if depth >= MAX_FILTER_DEPTH {
+                    return None;
+                }
+                let count = value.parse().ok()?;
+                let filters = (0..count)
+                    .map(|_| parse_one(tokens, depth + 1))
+                    .collect::<Option<_>>()?;
+                Some(if key == "nor" {
+                    Filter::Nor(filters)
+                } else {
+                    Filter::Nand(filters)
+                })
+            }
+            "dedicated" => flag(Filter::Dedicated),
+            "secure" => flag(Filter::Secure),
+            "gamedir" => Some(Filter::GameDir(value.into())),
+            "map" => Some(Filter::Map(value.into())),
+            "linux" => flag(Filter::Linux),
+            "password" => Some(Filter::NoPassword).filter(|_| value == "0"),
+            "empty" => flag(Filter::NotEmpty),
+            "full" => flag(Filter::NotFull),
+            "proxy" => flag(Filter::Proxy),
+            "appid" => Some(Filter::Appid(value.into())),
+            "napp" => Some(Filter::NotAppid(value.into())),
+            "noplayers" => flag(Filter::NoPlayers),
+            "white" => flag(Filter::Whitelisted),
+            "gametype" => Some(Filter::GameType(value.into())),
+            "gamedata" => Some(Filter::GameDataAll(value.into())),
+            "gamedataor" => Some(Filter::GameDataAny(value.into())),
+            "name_match" => Some(Filter::NameMatch(value.into())),
+            "version_match" => Some(Filter::VersionMatch(value.into())),
+            "collapse_addr_hash" => flag(Filter::CollapseAddrHash),
+            "gameaddr" => value
+                .parse()
+                .map(Filter::GameAddr)
+                .or_else(|_| value.parse().map(Filter::GameIp))
+                .ok(),
+            _ => None,
+        }
     }
 
-    pub fn request(
-        &self,
-        seed: &SocketAddrV4,
-        region: Region,
-        filters: &[Filter],
-    ) -> QueryResult<Vec<SocketAddrV4>> {
-        let data = self.raw_request(
-            0x31,
-            region as u8,
-            seed.to_string(),
-            filters.iter().map(|f| format!("{}", f)).collect::<String>(),
-        )?;
-
-        let (_, reply) = Reply::parse(&data)?;
-        Ok(reply.addresses)
+    if wire.is_empty() {
+        return Some(vec![]);
     }
-
-    pub fn iter<'a>(&'a self, region: Region, filters: &'a [Filter]) -> MasterQueryIter<'a> {
-        MasterQueryIter::new(self, region, filters)
+    let mut tokens = wire.strip_prefix('\\')?.split('\\').peekable();
+    let mut filters = vec![];
+    while tokens.peek().is_some() {
+        filters.push(parse_one(&mut tokens, 0)?);
     }
+    Some(filters)
 }
 
-pub struct MasterQueryIter<'a> {
-    region: Region,
-    filters: &'a [Filter],
-    query: &'a ServersQuery,
-    buf: Vec<SocketAddrV4>,
-    index: usize,
+/// Request for a page of servers after `seed`, which is `0.0.0.0:0` for the first page.
+pub fn request_bytes(seed: &SocketAddrV4, region: Region, filters: &[Filter]) -> Vec<u8> {
+    let seed = seed.to_string();
+    let filter: String = filters.iter().map(|f| format!("{}", f)).collect();
+    let mut data = Vec::with_capacity(4 + seed.len() + filter.len());
+    data.push(0x31);
+    data.push(region as u8);
+    data.extend(seed.as_bytes());
+    data.push(0);
+    data.extend(filter.as_bytes());
+    data.push(0);
+    data
 }
 
-impl<'a> MasterQueryIter<'a> {
-    fn new(query: &'a ServersQuery, region: Region, filters: &'a [Filter]) -> Self {
-        Self {
-            region,
-            filters,
-            query,
-            buf: vec![],
-            index: 0,
-        }
-    }
-}
-
-impl<'a> Iterator for MasterQueryIter<'a> {
-    type Item = QueryResult<SocketAddrV4>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        let nul_addr = SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 0);
-        let mut val = self.buf.get(self.index);
-
-        if val.is_none() {
-            let seed = self.buf.last().unwrap_or(&nul_addr);
-            if seed == &nul_addr {
-                // If last element is nul_addr
-                if !self.buf.is_empty() {
-                    return None;
-                } else {
-                    self.index = 0;
-                }
-            } else {
-                self.index = 1;
-            }
-            let reply = self.query.request(seed, self.region, self.filters);
-            match reply {
-                Ok(reply) => {
-                    self.buf.clear();
-                    self.buf.extend_from_slice(&reply);
-                    val = self.buf.get(self.index);
-                }
-                Err(err) => return Some(Err(err)),
-            }
-        }
-        self.index += 1;
-        val.copied().map(Ok)
-    }
+/// Addresses of servers in a reply, the last one is `0.0.0.0:0` if there are no more pages.
+pub fn decode_reply(data: &[u8]) -> QueryResult<Vec<SocketAddrV4>> {
+    let (_, reply) =
+        Reply::parse(data).map_err(|err| ParseError::new("master reply", data, err))?;
+    Ok(reply.addresses)
 }
@@ -0,0 +1,207 @@
+use super::{Error, Filter, Region, ServersQuery};
+use crate::{Limits, RateLimiter};
+use std::{
+    collections::HashMap,
+    io::Result as IOResult,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4},
+    sync::Arc,
+    thread,
+    time::Duration,
+    vec::IntoIter,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PoolMode {
+    /// Crawls every master at once and merges their lists.
+    Parallel,
+    /// Crawls masters one by one until one of them returns the whole list.
+    Failover,
+}
+
+/// Outcome of crawling a single master.
+#[derive(Debug)]
+pub struct MasterReport {
+    pub master: SocketAddr,
+    /// Unique servers returned by the master in the order of pages, including duplicates
+    /// of other masters.
+    pub servers: Vec<SocketAddrV4>,
+    pub pages: usize,
+    /// Error which stopped the crawl, the list is partial then.
+    pub error: Option<Error>,
+}
+
+/// Servers merged from several masters.
+#[derive(Debug)]
+pub struct PoolCrawl {
+    servers: Vec<SocketAddrV4>,
+    reports: Vec<MasterReport>,
+    sources: HashMap<SocketAddrV4, Vec<SocketAddr>>,
+}
+
+impl PoolCrawl {
+    /// Unique servers in the order they were first seen.
+    pub fn servers(&self) -> &[SocketAddrV4] {
+        &self.servers
+    }
+
+    /// Reports of the crawled masters in the order of the pool.
+    pub fn reports(&self) -> &[MasterReport] {
+        &self.reports
+    }
+
+    /// Masters which returned `server`.
+    pub fn sources(&self, server: &SocketAddrV4) -> &[SocketAddr] {
+        self.sources.get(server).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether every crawled master failed.
+    pub fn is_failed(&self) -> bool {
+        self.reports.iter().all(|report| report.error.is_some())
+    }
+}
+
+impl IntoIterator for PoolCrawl {
+    type Item = SocketAddrV4;
+    type IntoIter = IntoIter<SocketAddrV4>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.servers.into_iter()
+    }
+}
+
+/// Several master servers queried as one, e.g. every address of `hl2master.steampowered.com`.
+pub struct MasterPool {
+    masters: Vec<SocketAddr>,
+    mode: PoolMode,
+    timeout: Duration,
+    limits: Limits,
+    limiter: Option<Arc<RateLimiter>>,
+    max_pages: usize,
+}
+
+impl MasterPool {
+    pub fn new(masters: Vec<SocketAddr>) -> Self {
+        Self {
+            masters,
+            mode: PoolMode::Parallel,
+            timeout: Duration::from_secs(5),
+            limits: Limits::default(),
+            limiter: None,
+            max_pages: 1000,
+        }
+    }
+
+    pub fn masters(&self) -> &[SocketAddr] {
+        &self.masters
+    }
+
+    pub fn set_mode(&mut self, mode: PoolMode) {
+        self.mode = mode;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn set_rate_limiter(&mut self, limiter: Option<Arc<RateLimiter>>) {
+        self.limiter = limiter;
+    }
+
+    /// See `MasterQueryIter::set_max_pages`.
+    pub fn set_max_pages(&mut self, pages: usize) {
+        self.max_pages = pages;
+    }
+
+    pub fn crawl(&self, region: Region, filters: &[Filter]) -> PoolCrawl {
+        let reports = match self.mode {
+            PoolMode::Parallel => thread::scope(|scope| {
+                let crawls: Vec<_> = self
+                    .masters
+                    .iter()
+                    .map(|&master| scope.spawn(move || self.crawl_master(master, region, filters)))
+                    .collect();
+                crawls
+                    .into_iter()
+                    .map(|crawl| crawl.join().unwrap())
+                    .collect()
+            }),
+            PoolMode::Failover => {
+                let mut reports = vec![];
+                for &master in &self.masters {
+                    let report = self.crawl_master(master, region, filters);
+                    let done = report.error.is_none();
+                    reports.push(report);
+                    if done {
+                        break;
+                    }
+                }
+                reports
+            }
+        };
+
+        let mut servers = vec![];
+        let mut sources: HashMap<_, Vec<_>> = HashMap::new();
+        for report in &reports {
+            for &server in &report.servers {
+                let masters = sources.entry(server).or_insert_with(|| {
+                    servers.push(server);
+                    vec![]
+                });
+                // Servers of a report are unique, so a master is added once
+                masters.push(report.master);
+            }
+        }
+        PoolCrawl {
+            servers,
+            reports,
+            sources,
+        }
+    }
+
+    fn crawl_master(&self, master: SocketAddr, region: Region, filters: &[Filter]) -> MasterReport {
+        let mut report = MasterReport {
+            master,
+            servers: vec![],
+            pages: 0,
+            error: None,
+        };
+        let query = match self.connect(master) {
+            Ok(query) => query,
+            Err(err) => {
+                report.error = Some(err.into());
+                return report;
+            }
+        };
+
+        let mut servers = query.iter(region, filters);
+        servers.set_max_pages(self.max_pages);
+        for server in &mut servers {
+            match server {
+                Ok(server) => report.servers.push(server),
+                Err(err) => {
+                    report.error = Some(err);
+                    break;
+                }
+            }
+        }
+        report.pages = servers.pages();
+        report
+    }
+
+    fn connect(&self, master: SocketAddr) -> IOResult<ServersQuery> {
+        let local: SocketAddr = match master {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let mut query = ServersQuery::bind(local)?;
+        query.set_timeout(Some(self.timeout))?;
+        query.set_limits(self.limits);
+        query.set_rate_limiter(self.limiter.clone());
+        query.connect(master)?;
+        Ok(query)
+    }
+}
@@ -0,0 +1,242 @@
+use super::{decode_reply, request_bytes, Error, Filter, NotTerminated, QueryResult, Region};
+use crate::{
+    transport::Failover, ErrorKind, LimitExceeded, LimitKind, Limits, RateLimiter, Transport,
+};
+use std::{
+    collections::HashSet,
+    io::Result as IOResult,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, ToSocketAddrs, UdpSocket},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const NUL_ADDR: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0);
+
+pub struct ServersQuery<T: Transport = UdpSocket> {
+    transport: T,
+    limiter: Option<Arc<RateLimiter>>,
+    limits: Limits,
+    failover: Mutex<Failover>,
+}
+
+impl ServersQuery {
+    pub fn bind(addr: SocketAddr) -> IOResult<Self> {
+        Ok(Self::with_transport(UdpSocket::bind(addr)?))
+    }
+
+    /// Connects to the first resolved address of `addrs`, e.g. `hl2master.steampowered.com:27011`.
+    /// If a request times out, it's repeated with the next one, which is used afterwards
+    /// if it replies.
+    pub fn connect<A: ToSocketAddrs>(&self, addrs: A) -> IOResult<()> {
+        *self.failover.lock().unwrap() = Failover::connect(&self.transport, addrs)?;
+        Ok(())
+    }
+
+    pub fn timeout(&self) -> IOResult<Option<Duration>> {
+        self.transport.read_timeout()
+    }
+
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> IOResult<()> {
+        self.transport.set_read_timeout(timeout)
+    }
+}
+
+impl<T: Transport> ServersQuery<T> {
+    /// Query over another transport than UDP socket, e.g. `Replay` of recorded datagrams.
+    pub fn with_transport(transport: T) -> Self {
+        Self {
+            transport,
+            limiter: None,
+            limits: Limits::default(),
+            failover: Mutex::default(),
+        }
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Limiter of requests to master servers, which ban clients sending too many of them.
+    pub fn set_rate_limiter(&mut self, limiter: Option<Arc<RateLimiter>>) {
+        self.limiter = limiter;
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Only `Limits::master_reply` applies to master servers.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn request(
+        &self,
+        seed: &SocketAddrV4,
+        region: Region,
+        filters: &[Filter],
+    ) -> QueryResult<Vec<SocketAddrV4>> {
+        let request = request_bytes(seed, region, filters);
+        // Failover is only locked around its updates, so requests don't wait for each other
+        let mut attempts = self.failover.lock().unwrap().attempts();
+        loop {
+            let current = self.failover.lock().unwrap().current();
+            match self.request_once(&request) {
+                Err(err) if err.kind() == ErrorKind::Timeout && attempts > 1 => {
+                    self.failover
+                        .lock()
+                        .unwrap()
+                        .advance(&self.transport, current)?;
+                    attempts -= 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn request_once(&self, request: &[u8]) -> QueryResult<Vec<SocketAddrV4>> {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire(self.transport.peer_addr()?);
+        }
+        self.transport.send(request)?;
+
+        // One byte more than allowed to detect too large replies, which are truncated otherwise
+        let limit = self.limits.master_reply;
+        let mut buf = vec![0; limit.saturating_add(1)];
+        let size = self.transport.recv(&mut buf)?;
+        LimitExceeded::check(LimitKind::MasterReply, limit, size)?;
+        decode_reply(&buf[..size])
+    }
+
+    pub fn iter<'a>(&'a self, region: Region, filters: &'a [Filter]) -> MasterQueryIter<'a, T> {
+        MasterQueryIter::new(self, region, filters)
+    }
+}
+
+/// Servers of all pages, which ends with an error if the master never ends the list
+/// or fails to reply too many times in a row.
+pub struct MasterQueryIter<'a, T: Transport = UdpSocket> {
+    region: Region,
+    filters: &'a [Filter],
+    query: &'a ServersQuery<T>,
+    buf: Vec<SocketAddrV4>,
+    index: usize,
+    // `None` once the last page is received
+    next_seed: Option<SocketAddrV4>,
+    seeds: HashSet<SocketAddrV4>,
+    // `None` if duplicates are yielded
+    seen: Option<HashSet<SocketAddrV4>>,
+    pages: usize,
+    max_pages: usize,
+    // Consecutive failed requests
+    failures: usize,
+    max_failures: usize,
+    done: bool,
+}
+
+impl<'a, T: Transport> MasterQueryIter<'a, T> {
+    fn new(query: &'a ServersQuery<T>, region: Region, filters: &'a [Filter]) -> Self {
+        Self {
+            region,
+            filters,
+            query,
+            buf: vec![],
+            index: 0,
+            next_seed: Some(NUL_ADDR),
+            seeds: HashSet::new(),
+            seen: Some(HashSet::new()),
+            pages: 0,
+            max_pages: 1000,
+            failures: 0,
+            max_failures: 3,
+            done: false,
+        }
+    }
+
+    /// Whether to skip servers, which were yielded already, on by default.
+    pub fn set_dedup(&mut self, dedup: bool) {
+        self.seen = if dedup { Some(HashSet::new()) } else { None };
+    }
+
+    /// Number of pages after which the crawl fails with `NotTerminated::PageLimit`,
+    /// 1000 by default.
+    pub fn set_max_pages(&mut self, pages: usize) {
+        self.max_pages = pages;
+    }
+
+    /// Number of consecutive failed requests of a page after which the crawl ends,
+    /// 3 by default. Every failure is yielded as an error.
+    pub fn set_max_failures(&mut self, failures: usize) {
+        self.max_failures = failures;
+    }
+
+    /// Number of received pages.
+    pub fn pages(&self) -> usize {
+        self.pages
+    }
+
+    fn fetch(&mut self, seed: SocketAddrV4) -> QueryResult<()> {
+        if self.pages >= self.max_pages {
+            return Err(NotTerminated::PageLimit(self.pages).into());
+        }
+        if !self.seeds.insert(seed) {
+            return Err(NotTerminated::RepeatedSeed(seed).into());
+        }
+        let page = self
+            .query
+            .request(&seed, self.region, self.filters)
+            .inspect_err(|_| {
+                // Same page is requested again by the next call
+                self.seeds.remove(&seed);
+                self.failures += 1;
+            })?;
+        self.failures = 0;
+        self.pages += 1;
+        if page.is_empty() {
+            return Err(NotTerminated::EmptyPage.into());
+        }
+        self.next_seed = page.last().copied().filter(|&last| last != NUL_ADDR);
+        // Every page after the first one starts with its seed
+        self.index = usize::from(page.first() == Some(&seed) && seed != NUL_ADDR);
+        self.buf = page;
+        Ok(())
+    }
+}
+
+impl<'a, T: Transport> Iterator for MasterQueryIter<'a, T> {
+    type Item = QueryResult<SocketAddrV4>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let server = match self.buf.get(self.index) {
+                Some(&server) => server,
+                None => {
+                    let seed = match self.next_seed {
+                        Some(seed) => seed,
+                        None => break,
+                    };
+                    match self.fetch(seed) {
+                        Ok(()) => continue,
+                        Err(err) => {
+                            self.done = matches!(err, Error::NotTerminated(_))
+                                || self.failures >= self.max_failures;
+                            return Some(Err(err));
+                        }
+                    }
+                }
+            };
+            self.index += 1;
+            if server == NUL_ADDR {
+                break;
+            }
+            if let Some(seen) = &mut self.seen {
+                if !seen.insert(server) {
+                    continue;
+                }
+            }
+            return Some(Ok(server));
+        }
+        self.done = true;
+        None
+    }
+}
@@ -1,7 +1,9 @@
-use nom_derive::Nom;
-use std::net::{Ipv4Addr, SocketAddrV4};
+use crate::parse::{field, FieldResult};
+use alloc::vec::Vec;
+use core::net::{Ipv4Addr, SocketAddrV4};
+use nom::{bytes::streaming::tag, multi::many0};
 
-fn take_socket_addr(i: &[u8]) -> nom::IResult<&[u8], SocketAddrV4> {
+fn take_socket_addr(i: &[u8]) -> FieldResult<'_, SocketAddrV4> {
     use nom::number::complete::{be_u16, le_u8};
     let (i, (first, second, third, fourth, port)) =
         nom::sequence::tuple((le_u8, le_u8, le_u8, le_u8, be_u16))(i)?;
@@ -11,10 +13,14 @@ fn take_socket_addr(i: &[u8]) -> nom::IResult<&[u8], SocketAddrV4> {
     ))
 }
 
-#[derive(Nom)]
-pub struct Reply<'a> {
-    #[nom(Tag(b"\xFF\xFF\xFF\xFF\x66\x0A"))]
-    _header: &'a [u8],
-    #[nom(Parse = "nom::multi::many0(take_socket_addr)")]
+pub struct Reply {
     pub addresses: Vec<SocketAddrV4>,
 }
+
+impl Reply {
+    pub fn parse(i: &[u8]) -> FieldResult<'_, Self> {
+        let (i, _) = field("header", tag(b"\xFF\xFF\xFF\xFF\x66\x0A"))(i)?;
+        let (i, addresses) = many0(take_socket_addr)(i)?;
+        Ok((i, Self { addresses }))
+    }
+}
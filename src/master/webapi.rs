@@ -0,0 +1,343 @@
+//! Server list of the Steam Web API (`IGameServersService/GetServerList`), which replaces
+//! the deprecated UDP protocol of master servers and takes the same filters.
+//!
+//! ```no_run
+//! use vquery::master::{Filter, PlainHttp, WebApiQuery};
+//!
+//! // Plain HTTP only, e.g. a local proxy in front of `STEAM_API_URL`
+//! let query = WebApiQuery::new(PlainHttp::default(), "http://127.0.0.1:8080", "STEAM_API_KEY");
+//! for server in query.iter(&[Filter::Appid("730".into())]) {
+//!     println!("{:?}", server.unwrap());
+//! }
+//! ```
+use super::{Error, Filter, QueryResult};
+use serde_json::Value;
+use std::{
+    convert::TryFrom,
+    io::{Error as IOError, ErrorKind, Read, Result as IOResult, Write},
+    net::{Ipv6Addr, SocketAddr, TcpStream, ToSocketAddrs},
+    time::Duration,
+    vec::IntoIter,
+};
+
+pub const STEAM_API_URL: &str = "https://api.steampowered.com";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+/// Performs GET requests of `WebApiQuery`, e.g. over a TLS capable HTTP client.
+pub trait HttpClient {
+    fn get(&self, url: &str) -> IOResult<HttpResponse>;
+}
+
+/// Minimal HTTP/1.0 client without TLS, so only `http://` URLs are supported.
+#[derive(Debug, Clone)]
+pub struct PlainHttp {
+    timeout: Duration,
+    max_size: usize,
+}
+
+impl Default for PlainHttp {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(10),
+            max_size: 16 * 1024 * 1024,
+        }
+    }
+}
+
+impl PlainHttp {
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Largest accepted response including headers.
+    pub fn set_max_size(&mut self, size: usize) {
+        self.max_size = size;
+    }
+}
+
+impl HttpClient for PlainHttp {
+    fn get(&self, url: &str) -> IOResult<HttpResponse> {
+        // Query string holds the API key, so it's left out of errors
+        let location = url.split('?').next().unwrap_or_default();
+        let invalid =
+            |msg: &str| IOError::new(ErrorKind::InvalidInput, format!("{}: {}", msg, location));
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| invalid("Only http:// URLs are supported"))?;
+        let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let (name, port) = split_host(host).ok_or_else(|| invalid("Invalid host"))?;
+        let address: SocketAddr = (name, port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| invalid("Can't resolve the host"))?;
+
+        let mut stream = TcpStream::connect_timeout(&address, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        // Whole request at once, so a server can't close the connection in the middle of it
+        let request = format!(
+            "GET {} HTTP/1.0\r\nHost: {}\r\nAccept: application/json\r\n\r\n",
+            if path.is_empty() { "/" } else { path },
+            host_header(name, port)
+        );
+        stream.write_all(request.as_bytes())?;
+
+        let mut response = vec![];
+        stream
+            .take(self.max_size as u64 + 1)
+            .read_to_end(&mut response)?;
+        if response.len() > self.max_size {
+            return Err(IOError::new(
+                ErrorKind::InvalidData,
+                "HTTP response is too large",
+            ));
+        }
+        parse_response(response)
+    }
+}
+
+/// Splits `host` of a URL into a name and a port, IPv6 literals may go without brackets.
+fn split_host(host: &str) -> Option<(&str, u16)> {
+    if let Some(rest) = host.strip_prefix('[') {
+        let (name, port) = rest.split_once(']')?;
+        let port = match port {
+            "" => 80,
+            port => port.strip_prefix(':')?.parse().ok()?,
+        };
+        return Some((name, port));
+    }
+    if host.parse::<Ipv6Addr>().is_ok() {
+        return Some((host, 80));
+    }
+    match host.rsplit_once(':') {
+        Some((name, port)) => Some((name, port.parse().ok()?)),
+        None => Some((host, 80)),
+    }
+}
+
+/// Value of the `Host` header, where IPv6 literals are put in brackets.
+fn host_header(name: &str, port: u16) -> String {
+    let name = if name.contains(':') {
+        format!("[{}]", name)
+    } else {
+        name.to_owned()
+    };
+    if port == 80 {
+        name
+    } else {
+        format!("{}:{}", name, port)
+    }
+}
+
+fn parse_response(mut response: Vec<u8>) -> IOResult<HttpResponse> {
+    let malformed = || IOError::new(ErrorKind::InvalidData, "Malformed HTTP response");
+    let head_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(malformed)?;
+    let head = std::str::from_utf8(&response[..head_end]).map_err(|_| malformed())?;
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(malformed)?;
+    let length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .map(|(_, value)| value.trim().parse::<usize>().map_err(|_| malformed()))
+        .transpose()?;
+
+    let mut body = response.split_off(head_end + 4);
+    if let Some(length) = length {
+        if body.len() < length {
+            return Err(IOError::new(
+                ErrorKind::UnexpectedEof,
+                "Truncated HTTP body",
+            ));
+        }
+        body.truncate(length);
+    }
+    Ok(HttpResponse { status, body })
+}
+
+/// Server of a Web API reply, which includes a part of its A2S_INFO.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebServer {
+    /// Address of queries, which may differ from the game port.
+    pub addr: SocketAddr,
+    pub gameport: u16,
+    pub steamid: Option<u64>,
+    pub name: String,
+    pub appid: u32,
+    pub gamedir: String,
+    pub version: String,
+    pub product: String,
+    pub region: i32,
+    pub players: u32,
+    pub max_players: u32,
+    pub bots: u32,
+    pub map: String,
+    pub secure: bool,
+    pub dedicated: bool,
+    pub os: String,
+    pub gametype: String,
+}
+
+impl WebServer {
+    fn from_json(server: &Value) -> QueryResult<Self> {
+        let string = |key| server[key].as_str().unwrap_or_default().to_owned();
+        let number = |key| {
+            server[key]
+                .as_u64()
+                .and_then(|n| u32::try_from(n).ok())
+                .unwrap_or_default()
+        };
+        let flag = |key| server[key].as_bool().unwrap_or_default();
+        Ok(Self {
+            addr: server["addr"]
+                .as_str()
+                .and_then(|addr| addr.parse().ok())
+                .ok_or(Error::WebApiField("addr"))?,
+            gameport: server["gameport"]
+                .as_u64()
+                .and_then(|port| u16::try_from(port).ok())
+                .unwrap_or_default(),
+            steamid: server["steamid"].as_str().and_then(|id| id.parse().ok()),
+            name: string("name"),
+            appid: server["appid"]
+                .as_u64()
+                .and_then(|appid| u32::try_from(appid).ok())
+                .ok_or(Error::WebApiField("appid"))?,
+            gamedir: string("gamedir"),
+            version: string("version"),
+            product: string("product"),
+            region: server["region"]
+                .as_i64()
+                .and_then(|region| i32::try_from(region).ok())
+                .unwrap_or(-1),
+            players: number("players"),
+            max_players: number("max_players"),
+            bots: number("bots"),
+            map: string("map"),
+            secure: flag("secure"),
+            dedicated: flag("dedicated"),
+            os: string("os"),
+            gametype: string("gametype"),
+        })
+    }
+}
+
+/// Parses the body of a `GetServerList` reply. A malformed server is reported in its place,
+/// so it doesn't hide the rest of the list.
+pub fn decode_web_reply(body: &[u8]) -> QueryResult<Vec<QueryResult<WebServer>>> {
+    let reply: Value = serde_json::from_slice(body)?;
+    let response = reply
+        .get("response")
+        .ok_or(Error::WebApiField("response"))?;
+    // There's no `servers` at all if nothing matches the filters
+    match response.get("servers") {
+        None => Ok(vec![]),
+        Some(Value::Array(servers)) => Ok(servers.iter().map(WebServer::from_json).collect()),
+        Some(_) => Err(Error::WebApiField("servers")),
+    }
+}
+
+/// Percent-encoding of a query parameter.
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Queries servers through the Web API instead of UDP master servers.
+pub struct WebApiQuery<C: HttpClient> {
+    client: C,
+    key: String,
+    base_url: String,
+    limit: u32,
+}
+
+impl<C: HttpClient> WebApiQuery<C> {
+    /// `base_url` is `STEAM_API_URL` or a proxy in front of it, without `/IGameServersService`.
+    /// `key` is a Steam Web API key.
+    pub fn new(client: C, base_url: &str, key: &str) -> Self {
+        Self {
+            client,
+            key: key.into(),
+            base_url: base_url.trim_end_matches('/').into(),
+            limit: 10_000,
+        }
+    }
+
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    /// Maximal number of servers in a reply, the API has no pages.
+    pub fn set_limit(&mut self, limit: u32) {
+        self.limit = limit;
+    }
+
+    pub fn url(&self, filters: &[Filter]) -> String {
+        let filter: String = filters.iter().map(|f| f.to_string()).collect();
+        format!(
+            "{}/IGameServersService/GetServerList/v1/?key={}&limit={}&filter={}",
+            self.base_url,
+            encode(&self.key),
+            self.limit,
+            encode(&filter)
+        )
+    }
+
+    pub fn request(&self, filters: &[Filter]) -> QueryResult<Vec<QueryResult<WebServer>>> {
+        let response = self.client.get(&self.url(filters))?;
+        if response.status != 200 {
+            return Err(Error::HttpStatus(response.status));
+        }
+        decode_web_reply(&response.body)
+    }
+
+    pub fn iter<'a>(&'a self, filters: &'a [Filter]) -> WebApiIter<'a, C> {
+        WebApiIter {
+            query: self,
+            filters,
+            servers: None,
+        }
+    }
+}
+
+/// Same as `MasterQueryIter`, but the whole list is requested at once.
+pub struct WebApiIter<'a, C: HttpClient> {
+    query: &'a WebApiQuery<C>,
+    filters: &'a [Filter],
+    servers: Option<IntoIter<QueryResult<WebServer>>>,
+}
+
+impl<C: HttpClient> Iterator for WebApiIter<'_, C> {
+    type Item = QueryResult<WebServer>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.servers.is_none() {
+            match self.query.request(self.filters) {
+                Ok(servers) => self.servers = Some(servers.into_iter()),
+                Err(err) => {
+                    self.servers = Some(vec![].into_iter());
+                    return Some(Err(err));
+                }
+            }
+        }
+        self.servers.as_mut()?.next()
+    }
+}
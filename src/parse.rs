@@ -0,0 +1,200 @@
+use alloc::{format, string::String, vec, vec::Vec};
+use core::fmt::{Display, Formatter, Result as FmtResult, Write};
+use nom::{
+    error::{
+        context, ContextError, ErrorKind as NomErrorKind, FromExternalError,
+        ParseError as NomParseError,
+    },
+    IResult,
+};
+
+const EXCERPT_RADIUS: usize = 8;
+
+/// Describes where and why a reply couldn't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// Kind of the reply, e.g. `A2S_INFO`.
+    pub message: &'static str,
+    /// Path to the field, e.g. `players[3].name`.
+    pub field: String,
+    /// Offset of the offending byte inside of the reply.
+    pub offset: usize,
+    /// `None` if the reply ended before the field was complete.
+    pub kind: Option<NomErrorKind>,
+    /// Hex dump of the bytes around `offset`.
+    pub excerpt: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "Can't parse {} at field `{}` (offset {}): ",
+            self.message, self.field, self.offset
+        )?;
+        match self.kind {
+            Some(kind) => write!(f, "{} failed", kind.description())?,
+            None => write!(f, "reply is truncated")?,
+        }
+        write!(f, " [{}]", self.excerpt)
+    }
+}
+
+impl core::error::Error for ParseError {}
+
+impl ParseError {
+    /// Describes `error` of a parser of `data`, which fails in one of its fields.
+    pub(crate) fn new<'a>(
+        message: &'static str,
+        data: &'a [u8],
+        error: nom::Err<FieldError<'a>>,
+    ) -> Self {
+        let (offset, kind, field) = match error {
+            nom::Err::Error(e) | nom::Err::Failure(e) => {
+                let offset = (e.input.as_ptr() as usize).saturating_sub(data.as_ptr() as usize);
+                (offset.min(data.len()), e.kind, e.path())
+            }
+            nom::Err::Incomplete(_) => (data.len(), None, String::new()),
+        };
+        Self {
+            message,
+            field: if field.is_empty() {
+                String::from("<unknown>")
+            } else {
+                field
+            },
+            offset,
+            kind,
+            excerpt: hex_excerpt(data, offset),
+        }
+    }
+}
+
+/// Formats bytes around `offset` with the offending one in brackets.
+fn hex_excerpt(data: &[u8], offset: usize) -> String {
+    let start = offset.saturating_sub(EXCERPT_RADIUS);
+    let end = data.len().min(offset + EXCERPT_RADIUS + 1);
+    let mut excerpt = format!("{:#06x}:", start);
+    for (pos, byte) in data.iter().enumerate().take(end).skip(start) {
+        if pos == offset {
+            write!(excerpt, " [{:02x}]", byte).unwrap();
+        } else {
+            write!(excerpt, " {:02x}", byte).unwrap();
+        }
+    }
+    if offset >= data.len() {
+        excerpt.push_str(" [end]");
+    }
+    excerpt
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Field(&'static str),
+    Index(usize),
+}
+
+/// Error of the reply parsers, which remembers the path to the field that failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError<'a> {
+    /// Input of the failed parser.
+    pub input: &'a [u8],
+    /// `None` if the reply ended before the field was complete.
+    pub kind: Option<NomErrorKind>,
+    // Innermost segment first
+    path: Vec<Segment>,
+}
+
+impl FieldError<'_> {
+    /// Path to the field, e.g. `players[3].name`.
+    pub fn path(&self) -> String {
+        let mut path = String::new();
+        for segment in self.path.iter().rev() {
+            match segment {
+                Segment::Field(name) => {
+                    if !path.is_empty() {
+                        path.push('.');
+                    }
+                    path.push_str(name);
+                }
+                Segment::Index(index) => write!(path, "[{}]", index).unwrap(),
+            }
+        }
+        path
+    }
+}
+
+impl<'a> NomParseError<&'a [u8]> for FieldError<'a> {
+    fn from_error_kind(input: &'a [u8], kind: NomErrorKind) -> Self {
+        Self {
+            input,
+            kind: Some(kind),
+            path: vec![],
+        }
+    }
+
+    fn append(_: &'a [u8], _: NomErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<'a> ContextError<&'a [u8]> for FieldError<'a> {
+    fn add_context(_: &'a [u8], name: &'static str, mut other: Self) -> Self {
+        other.path.push(Segment::Field(name));
+        other
+    }
+}
+
+impl<'a, E> FromExternalError<&'a [u8], E> for FieldError<'a> {
+    fn from_external_error(input: &'a [u8], kind: NomErrorKind, _: E) -> Self {
+        Self::from_error_kind(input, kind)
+    }
+}
+
+/// Result of the reply parsers, e.g. `InfoNew::parse`.
+pub type FieldResult<'a, O> = IResult<&'a [u8], O, FieldError<'a>>;
+
+/// Names the value parsed by `parser` in errors. A truncated value is reported at the end
+/// of the input, as `Err::Incomplete` has no place to point at.
+pub(crate) fn field<'a, O, F>(
+    name: &'static str,
+    mut parser: F,
+) -> impl FnMut(&'a [u8]) -> FieldResult<'a, O>
+where
+    F: FnMut(&'a [u8]) -> FieldResult<'a, O>,
+{
+    context(name, move |i: &'a [u8]| {
+        parser(i).map_err(|err| match err {
+            nom::Err::Incomplete(_) => nom::Err::Error(FieldError {
+                input: &i[i.len()..],
+                kind: None,
+                path: vec![],
+            }),
+            err => err,
+        })
+    })
+}
+
+/// Same as `nom::multi::count`, but adds the index of the failed value to the path.
+pub(crate) fn indexed<'a, O, F>(
+    mut parser: F,
+    count: usize,
+) -> impl FnMut(&'a [u8]) -> FieldResult<'a, Vec<O>>
+where
+    F: FnMut(&'a [u8]) -> FieldResult<'a, O>,
+{
+    move |mut i| {
+        let mut values = Vec::new();
+        for index in 0..count {
+            let (rest, value) = parser(i).map_err(|err| {
+                err.map(|mut err| {
+                    err.path.push(Segment::Index(index));
+                    err
+                })
+            })?;
+            values.push(value);
+            i = rest;
+        }
+        Ok((i, values))
+    }
+}
@@ -0,0 +1,406 @@
+//! Replay of A2S traffic captured by tcpdump or Wireshark in pcap and pcapng files.
+//!
+//! ```no_run
+//! use vquery::{pcap::replay, server::SourceParser};
+//!
+//! let capture = std::fs::read("broken-server.pcap").unwrap();
+//! for record in replay::<SourceParser>(&capture, &[27015]).unwrap() {
+//!     match record.reply {
+//!         Ok(reply) => println!("{} -> {}: {:?}", record.server, record.client, reply),
+//!         Err(err) => println!("{} -> {}: {}", record.server, record.client, err),
+//!     }
+//! }
+//! ```
+use crate::{
+    master,
+    server::{
+        decode_challenge, decode_info_new, decode_info_old, decode_players, decode_rules,
+        Error as ServerError, InfoNew, InfoOld, PacketParser, PlayersList, Progress, Reassembler,
+        RulesList,
+    },
+};
+use nom::{
+    bytes::complete::take,
+    number::{
+        complete::{u16 as nom_u16, u32 as nom_u32},
+        Endianness,
+    },
+};
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4},
+    time::Duration,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Unknown capture format, magic number is {0:#010x}")]
+    UnknownFormat(u32),
+    #[error("Capture is truncated at offset {0}")]
+    Truncated(usize),
+    #[error("Unsupported link type {0}")]
+    UnsupportedLinkType(u32),
+}
+
+/// Captured link-layer frame.
+struct Frame<'a> {
+    link_type: u32,
+    /// Since the UNIX epoch.
+    timestamp: Duration,
+    data: &'a [u8],
+}
+
+type NomResult<'a, O> = nom::IResult<&'a [u8], O>;
+
+/// Maps a nom error to the offset in the capture.
+fn truncated(capture: &[u8]) -> impl Fn(nom::Err<nom::error::Error<&[u8]>>) -> Error + '_ {
+    move |err| match err {
+        nom::Err::Error(err) | nom::Err::Failure(err) => {
+            Error::Truncated(capture.len() - err.input.len())
+        }
+        nom::Err::Incomplete(_) => Error::Truncated(capture.len()),
+    }
+}
+
+fn frames(capture: &[u8]) -> Result<Vec<Frame<'_>>, Error> {
+    let (_, magic) = nom_u32::<_, ()>(Endianness::Little)(capture)
+        .map_err(|_| Error::Truncated(capture.len()))?;
+    match magic {
+        0xA1B2_C3D4 | 0xA1B2_3C4D | 0xD4C3_B2A1 | 0x4D3C_B2A1 => pcap_frames(capture),
+        0x0A0D_0D0A => pcapng_frames(capture),
+        _ => return Err(Error::UnknownFormat(magic)),
+    }
+    .map(|(_, frames)| frames)
+    .map_err(truncated(capture))
+}
+
+fn pcap_frames(capture: &[u8]) -> NomResult<'_, Vec<Frame<'_>>> {
+    let (_, magic) = nom_u32(Endianness::Big)(capture)?;
+    let endianness = match magic {
+        0xA1B2_C3D4 | 0xA1B2_3C4D => Endianness::Big,
+        _ => Endianness::Little,
+    };
+    let nanos = magic == 0xA1B2_3C4D || magic == 0x4D3C_B2A1;
+    let (i, _) = take(20_usize)(capture)?;
+    let (mut i, link_type) = nom_u32(endianness)(i)?;
+
+    let mut frames = vec![];
+    while !i.is_empty() {
+        let (rest, seconds) = nom_u32(endianness)(i)?;
+        let (rest, fraction) = nom_u32(endianness)(rest)?;
+        let (rest, captured_len) = nom_u32(endianness)(rest)?;
+        let (rest, _) = nom_u32(endianness)(rest)?;
+        let (rest, data) = take(captured_len)(rest)?;
+        let fraction = if nanos {
+            Duration::from_nanos(fraction.into())
+        } else {
+            Duration::from_micros(fraction.into())
+        };
+        frames.push(Frame {
+            link_type,
+            timestamp: Duration::from_secs(seconds.into()) + fraction,
+            data,
+        });
+        i = rest;
+    }
+    Ok((i, frames))
+}
+
+struct Interface {
+    link_type: u32,
+    /// Timestamp units per second.
+    resolution: u64,
+}
+
+fn pcapng_frames(capture: &[u8]) -> NomResult<'_, Vec<Frame<'_>>> {
+    let mut endianness = Endianness::Little;
+    let mut interfaces = vec![];
+    let mut frames = vec![];
+    let mut i = capture;
+    while !i.is_empty() {
+        let (rest, block_type) = nom_u32(endianness)(i)?;
+        if block_type == 0x0A0D_0D0A {
+            // Section header defines byte order of the following blocks
+            let (_, magic) = nom_u32(Endianness::Little)(&rest[4.min(rest.len())..])?;
+            endianness = if magic == 0x1A2B_3C4D {
+                Endianness::Little
+            } else {
+                Endianness::Big
+            };
+            interfaces.clear();
+        }
+        let (rest, block_len) = nom_u32(endianness)(rest)?;
+        // Block length covers type, length and trailing length, so it's at least 12
+        let body_len = (block_len as usize).saturating_sub(12);
+        let (rest, body) = take(body_len)(rest)?;
+        let (rest, _) = nom_u32(endianness)(rest)?;
+        i = rest;
+
+        match block_type {
+            1 => interfaces.push(pcapng_interface(body, endianness)?),
+            3 => {
+                let (data, original_len) = nom_u32(endianness)(body)?;
+                let data = &data[..data.len().min(original_len as usize)];
+                if let Some(interface) = interfaces.first() {
+                    frames.push(Frame {
+                        link_type: interface.link_type,
+                        timestamp: Duration::ZERO,
+                        data,
+                    });
+                }
+            }
+            6 => {
+                let (body, interface) = nom_u32(endianness)(body)?;
+                let (body, high) = nom_u32(endianness)(body)?;
+                let (body, low) = nom_u32(endianness)(body)?;
+                let (body, captured_len) = nom_u32(endianness)(body)?;
+                let (body, _) = nom_u32(endianness)(body)?;
+                let (_, data) = take(captured_len)(body)?;
+                if let Some(interface) = interfaces.get(interface as usize) {
+                    let ticks = u64::from(high) << 32 | u64::from(low);
+                    let nanos =
+                        u128::from(ticks) * 1_000_000_000 / u128::from(interface.resolution);
+                    frames.push(Frame {
+                        link_type: interface.link_type,
+                        timestamp: Duration::from_nanos(nanos.try_into().unwrap_or(u64::MAX)),
+                        data,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok((i, frames))
+}
+
+fn pcapng_interface(
+    body: &[u8],
+    endianness: Endianness,
+) -> Result<Interface, nom::Err<nom::error::Error<&[u8]>>> {
+    let (i, link_type) = nom_u16(endianness)(body)?;
+    let (mut i, _) = take(6_usize)(i)?;
+    let mut resolution = 1_000_000;
+    while i.len() >= 4 {
+        let (rest, code) = nom_u16(endianness)(i)?;
+        let (rest, len) = nom_u16(endianness)(rest)?;
+        let (rest, value) = take(len)(rest)?;
+        // Options are padded to 32 bits
+        let (rest, _) = take(rest.len().min((4 - len as usize % 4) % 4))(rest)?;
+        match (code, value) {
+            (0, _) => break,
+            (9, &[tsresol]) => {
+                let exponent = u32::from(tsresol & 0x7F).min(63);
+                resolution = if tsresol & 0x80 == 0 {
+                    10_u64.checked_pow(exponent).unwrap_or(u64::MAX)
+                } else {
+                    1 << exponent
+                };
+            }
+            _ => {}
+        }
+        i = rest;
+    }
+    Ok(Interface {
+        link_type: link_type.into(),
+        resolution,
+    })
+}
+
+/// UDP datagram with its source and destination.
+struct Datagram<'a> {
+    source: SocketAddr,
+    destination: SocketAddr,
+    payload: &'a [u8],
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+/// IP packet in a frame, `None` if there is none.
+fn ip_packet(link_type: u32, data: &[u8]) -> Result<Option<&[u8]>, Error> {
+    let packet = match link_type {
+        // Ethernet with optional VLAN tags
+        1 => {
+            let mut offset = 12;
+            loop {
+                match u16_at(data, offset) {
+                    Some(0x8100) | Some(0x88A8) => offset += 4,
+                    Some(0x0800) | Some(0x86DD) => break data.get(offset + 2..),
+                    _ => return Ok(None),
+                }
+            }
+        }
+        // BSD loopback
+        0 | 108 => data.get(4..),
+        // Raw IP
+        12 | 14 | 101 | 228 | 229 => Some(data),
+        // Linux cooked capture v1 and v2
+        113 => data.get(16..),
+        276 => data.get(20..),
+        _ => return Err(Error::UnsupportedLinkType(link_type)),
+    };
+    Ok(packet)
+}
+
+/// UDP datagram in an IP packet, fragmented ones are skipped.
+fn udp_datagram(packet: &[u8]) -> Option<Datagram<'_>> {
+    let (source, destination, mut protocol, mut i) = match packet.first()? >> 4 {
+        4 => {
+            let header_len = usize::from(packet[0] & 0x0F) * 4;
+            let total_len = usize::from(u16_at(packet, 2)?);
+            if u16_at(packet, 6)? & 0x3FFF != 0 {
+                return None;
+            }
+            let source: [u8; 4] = packet.get(12..16)?.try_into().ok()?;
+            let destination: [u8; 4] = packet.get(16..20)?.try_into().ok()?;
+            (
+                IpAddr::from(Ipv4Addr::from(source)),
+                IpAddr::from(Ipv4Addr::from(destination)),
+                *packet.get(9)?,
+                packet.get(header_len..total_len.min(packet.len()))?,
+            )
+        }
+        6 => {
+            let payload_len = usize::from(u16_at(packet, 4)?);
+            let source: [u8; 16] = packet.get(8..24)?.try_into().ok()?;
+            let destination: [u8; 16] = packet.get(24..40)?.try_into().ok()?;
+            (
+                IpAddr::from(Ipv6Addr::from(source)),
+                IpAddr::from(Ipv6Addr::from(destination)),
+                *packet.get(6)?,
+                packet.get(40..(40 + payload_len).min(packet.len()))?,
+            )
+        }
+        _ => return None,
+    };
+    // Hop-by-hop, routing and destination options extension headers of IPv6
+    while let 0 | 43 | 60 = protocol {
+        protocol = *i.first()?;
+        i = i.get((usize::from(*i.get(1)?) + 1) * 8..)?;
+    }
+    if protocol != 17 {
+        return None;
+    }
+    let udp_len = usize::from(u16_at(i, 4)?);
+    Some(Datagram {
+        source: SocketAddr::new(source, u16_at(i, 0)?),
+        destination: SocketAddr::new(destination, u16_at(i, 2)?),
+        payload: i.get(8..udp_len.min(i.len()))?,
+    })
+}
+
+#[derive(Debug)]
+pub enum Reply {
+    Challenge(u32),
+    InfoNew(InfoNew),
+    InfoOld(InfoOld),
+    Players(PlayersList),
+    Rules(RulesList),
+    Master(Vec<SocketAddrV4>),
+}
+
+/// Reply found in a capture.
+#[derive(Debug)]
+pub struct Record {
+    /// Time of the last datagram of the reply since the UNIX epoch.
+    pub timestamp: Duration,
+    pub server: SocketAddr,
+    pub client: SocketAddr,
+    pub reply: Result<Reply, crate::Error>,
+}
+
+fn is_request(payload: &[u8]) -> bool {
+    match payload {
+        [0xFF, 0xFF, 0xFF, 0xFF, b'T', ..]
+        | [0xFF, 0xFF, 0xFF, 0xFF, b'U', ..]
+        | [0xFF, 0xFF, 0xFF, 0xFF, b'V', ..]
+        | [0xFF, 0xFF, 0xFF, 0xFF, b'W', ..] => true,
+        // Master server query
+        [0x31, ..] => true,
+        _ => false,
+    }
+}
+
+fn decode(payload: &[u8], app_id: i16) -> Result<Reply, crate::Error> {
+    let reply = match payload {
+        [b'A', ..] => decode_challenge(payload).map(Reply::Challenge),
+        [b'I', ..] => decode_info_new(payload).map(Reply::InfoNew),
+        [b'm', ..] => decode_info_old(payload).map(Reply::InfoOld),
+        [b'D', ..] => decode_players(payload, app_id).map(Reply::Players),
+        [b'E', ..] | [0xFF, 0xFF, 0xFF, 0xFF, b'E', ..] => decode_rules(payload).map(Reply::Rules),
+        _ => Err(ServerError::UnknownReply(payload.first().copied())),
+    };
+    reply.map_err(crate::Error::from)
+}
+
+/// Decodes every A2S and master server reply in a pcap or pcapng capture.
+///
+/// Only datagrams from or to one of `ports` are taken into account, any port if it's empty.
+/// Multi-packet replies are put together by `P`, replies of unknown types are recorded
+/// as `server::Error::UnknownReply`.
+pub fn replay<P: PacketParser>(capture: &[u8], ports: &[u16]) -> Result<Vec<Record>, Error> {
+    let mut reassemblers: HashMap<(SocketAddr, SocketAddr), Reassembler<P>> = HashMap::new();
+    // Players of some games are parsed according to the app id of the server
+    let mut app_ids: HashMap<SocketAddr, i16> = HashMap::new();
+    let mut records = vec![];
+
+    for frame in frames(capture)? {
+        let datagram = match ip_packet(frame.link_type, frame.data)?.and_then(udp_datagram) {
+            Some(datagram) => datagram,
+            None => continue,
+        };
+        let Datagram {
+            source: server,
+            destination: client,
+            payload,
+        } = datagram;
+        if !ports.is_empty() && !ports.contains(&server.port()) && !ports.contains(&client.port()) {
+            continue;
+        }
+        if !matches!(
+            payload,
+            [0xFF, 0xFF, 0xFF, 0xFF, ..] | [0xFE, 0xFF, 0xFF, 0xFF, ..]
+        ) || is_request(payload)
+        {
+            continue;
+        }
+        let mut record = |reply| {
+            records.push(Record {
+                timestamp: frame.timestamp,
+                server,
+                client,
+                reply,
+            })
+        };
+
+        if payload.starts_with(b"\xFF\xFF\xFF\xFF\x66\x0A") {
+            record(
+                master::decode_reply(payload)
+                    .map(Reply::Master)
+                    .map_err(Into::into),
+            );
+            continue;
+        }
+        let reassembler = reassemblers.entry((server, client)).or_default();
+        let payload = match reassembler.feed(payload) {
+            Ok(Progress::Complete(payload)) => payload,
+            Ok(Progress::NeedMore) => continue,
+            Err(err) => {
+                record(Err(ServerError::from(err).into()));
+                continue;
+            }
+        };
+        let app_id = app_ids.get(&server).copied().unwrap_or_default();
+        let reply = decode(&payload, app_id);
+        if let Ok(Reply::InfoNew(info)) = &reply {
+            app_ids.insert(server, info.steamid);
+        }
+        record(reply);
+    }
+    Ok(records)
+}
@@ -0,0 +1,142 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Number of destinations after which idle buckets are dropped.
+const PRUNE_THRESHOLD: usize = 4096;
+
+/// One request a day, so a wait for a token never takes longer than a day.
+const MIN_RATE: f64 = 1.0 / 86400.0;
+
+/// Sustained rate of requests per second with a burst allowed on top of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    rate: f64,
+    burst: u32,
+}
+
+impl Limit {
+    /// Rates below one request a day (including zero, negative and NaN ones) are raised to it,
+    /// and burst is at least 1.
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate: rate.max(MIN_RATE),
+            burst: burst.max(1),
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(limit: Limit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, limit: Limit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * limit.rate).min(limit.burst as f64);
+        self.updated = now;
+    }
+
+    /// Time until the bucket has a token.
+    fn wait(&self, limit: Limit) -> Duration {
+        Duration::from_secs_f64(((1.0 - self.tokens) / limit.rate).max(0.0))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Buckets {
+    global: Option<Bucket>,
+    destinations: HashMap<SocketAddr, Bucket>,
+}
+
+/// Token bucket limiter of outgoing requests, shared by queries through an `Arc`.
+///
+/// A request takes a token from the global bucket and from the bucket of its destination.
+#[derive(Debug)]
+pub struct RateLimiter {
+    global: Option<Limit>,
+    per_destination: Option<Limit>,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(global: Option<Limit>, per_destination: Option<Limit>) -> Self {
+        Self {
+            global,
+            per_destination,
+            buckets: Default::default(),
+        }
+    }
+
+    /// Takes tokens for a request to `destination`, or returns how long to wait for them.
+    pub fn try_acquire(&self, destination: SocketAddr) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let Buckets {
+            global,
+            destinations,
+        } = &mut *buckets;
+
+        let mut taken = vec![];
+        if let Some(limit) = self.global {
+            let bucket = global.get_or_insert_with(|| Bucket::full(limit, now));
+            bucket.refill(limit, now);
+            taken.push((bucket, limit));
+        }
+        if let Some(limit) = self.per_destination {
+            if destinations.len() >= PRUNE_THRESHOLD {
+                // Full buckets are the same as missing ones
+                destinations.retain(|_, bucket| {
+                    bucket.refill(limit, now);
+                    bucket.tokens < limit.burst as f64
+                });
+            }
+            let bucket = destinations
+                .entry(destination)
+                .or_insert_with(|| Bucket::full(limit, now));
+            bucket.refill(limit, now);
+            taken.push((bucket, limit));
+        }
+
+        let wait = taken
+            .iter()
+            .map(|(bucket, limit)| bucket.wait(*limit))
+            .max()
+            .unwrap_or_default();
+        if wait > Duration::ZERO {
+            return Err(wait);
+        }
+        for (bucket, _) in taken {
+            bucket.tokens -= 1.0;
+        }
+        Ok(())
+    }
+
+    /// Blocks until a request to `destination` is allowed.
+    pub fn acquire(&self, destination: SocketAddr) {
+        while let Err(wait) = self.try_acquire(destination) {
+            thread::sleep(wait);
+        }
+    }
+}
@@ -1,94 +1,179 @@
-use nom::number::streaming::le_f32;
-use nom_derive::Nom;
-use std::{ffi::CString, time::Duration};
+use super::{ExtraDataRef, InfoNewRef, PlayerRef, PlayersListRef, RuleRef, RulesListRef};
+use crate::parse::{field, FieldResult};
+use alloc::{borrow::ToOwned, ffi::CString, vec::Vec};
+use core::{ffi::CStr, time::Duration};
+use nom::{
+    bytes::streaming::{take, take_till},
+    combinator::{cond, map, map_res, recognize},
+    number::streaming::{le_i32, le_u8},
+    sequence::pair,
+};
 
-fn take_cstring(i: &[u8]) -> nom::IResult<&[u8], CString> {
-    let (i, cstr) = nom::bytes::streaming::take_till(|b| b == 0)(i)?;
-    let (i, _) = nom::bytes::streaming::take(1_usize)(i)?;
-    // Safety: safe because we already know that cstr doesn't contain nul-byte
-    let cstring = unsafe { CString::from_vec_unchecked(cstr.to_vec()) };
-    Ok((i, cstring))
+/// App id of The Ship, which extends A2S_INFO and A2S_PLAYER replies with its own fields.
+pub const THE_SHIP_APP_ID: i16 = 2400;
+
+pub(crate) fn take_cstr(i: &[u8]) -> FieldResult<'_, &CStr> {
+    map_res(
+        recognize(pair(take_till(|b| b == 0), take(1_usize))),
+        CStr::from_bytes_with_nul,
+    )(i)
+}
+
+fn take_cstring(i: &[u8]) -> FieldResult<'_, CString> {
+    map(take_cstr, CStr::to_owned)(i)
+}
+
+/// Same as `Duration::from_secs_f32`, but clamps negative, NaN and too large values
+/// instead of panicking on them.
+pub(crate) fn secs_f32(secs: f32) -> Duration {
+    if secs.is_nan() || secs <= 0.0 {
+        Duration::ZERO
+    } else {
+        Duration::try_from_secs_f32(secs).unwrap_or(Duration::MAX)
+    }
 }
 
-fn le_bool(i: &[u8]) -> nom::IResult<&[u8], bool> {
-    nom::combinator::map(nom::number::streaming::le_u8, |b| b != 0)(i)
+pub(crate) fn le_bool(i: &[u8]) -> FieldResult<'_, bool> {
+    map(le_u8, |b| b != 0)(i)
 }
 
-#[derive(Debug, Nom)]
-#[nom(LittleEndian)]
+#[derive(Debug)]
 pub struct ModData {
-    #[nom(Parse = "take_cstring")]
     pub link: CString,
-    #[nom(Parse = "take_cstring")]
     pub download_link: CString,
-    #[nom(AlignBefore(1))]
     pub version: i32,
     pub size: i32,
-    #[nom(Parse = "le_bool")]
     pub mp_only: bool,
-    #[nom(Parse = "le_bool")]
     pub custom_dll: bool,
 }
 
-#[derive(Debug, Nom)]
-#[nom(LittleEndian)]
+impl ModData {
+    pub fn parse(i: &[u8]) -> FieldResult<'_, Self> {
+        let (i, link) = field("link", take_cstring)(i)?;
+        let (i, download_link) = field("download_link", take_cstring)(i)?;
+        let (i, version) = field("version", le_i32)(i)?;
+        let (i, size) = field("size", le_i32)(i)?;
+        let (i, mp_only) = field("mp_only", le_bool)(i)?;
+        let (i, custom_dll) = field("custom_dll", le_bool)(i)?;
+        Ok((
+            i,
+            Self {
+                link,
+                download_link,
+                version,
+                size,
+                mp_only,
+                custom_dll,
+            },
+        ))
+    }
+}
+
+#[derive(Debug)]
 pub struct InfoOld {
-    #[nom(Parse = "take_cstring")]
     pub address: CString,
-    #[nom(Parse = "take_cstring")]
     pub name: CString,
-    #[nom(Parse = "take_cstring")]
     pub map: CString,
-    #[nom(Parse = "take_cstring")]
     pub folder: CString,
-    #[nom(Parse = "take_cstring")]
     pub game: CString,
     pub players: u8,
     pub max_players: u8,
     pub protocol: u8,
     pub server_type: u8,
     pub enviroment: u8,
-    #[nom(Parse = "le_bool")]
     pub is_private: bool,
-    #[nom(
-        PreExec = "let (i, mod_data_exists) = nom::number::streaming::le_u8(i)?;",
-        Cond = "mod_data_exists == 1"
-    )]
     pub mod_data: Option<ModData>,
-    #[nom(Parse = "le_bool")]
     pub vac_secured: bool,
     pub bots_num: u8,
 }
 
-#[derive(Debug, Nom)]
-#[nom(LittleEndian)]
+impl InfoOld {
+    pub fn parse(i: &[u8]) -> FieldResult<'_, Self> {
+        let (i, address) = field("address", take_cstring)(i)?;
+        let (i, name) = field("name", take_cstring)(i)?;
+        let (i, map) = field("map", take_cstring)(i)?;
+        let (i, folder) = field("folder", take_cstring)(i)?;
+        let (i, game) = field("game", take_cstring)(i)?;
+        let (i, players) = field("players", le_u8)(i)?;
+        let (i, max_players) = field("max_players", le_u8)(i)?;
+        let (i, protocol) = field("protocol", le_u8)(i)?;
+        let (i, server_type) = field("server_type", le_u8)(i)?;
+        let (i, enviroment) = field("enviroment", le_u8)(i)?;
+        let (i, is_private) = field("is_private", le_bool)(i)?;
+        let (i, mod_data_exists) = field("mod_data_exists", le_u8)(i)?;
+        let (i, mod_data) = cond(mod_data_exists == 1, field("mod_data", ModData::parse))(i)?;
+        let (i, vac_secured) = field("vac_secured", le_bool)(i)?;
+        let (i, bots_num) = field("bots_num", le_u8)(i)?;
+        Ok((
+            i,
+            Self {
+                address,
+                name,
+                map,
+                folder,
+                game,
+                players,
+                max_players,
+                protocol,
+                server_type,
+                enviroment,
+                is_private,
+                mod_data,
+                vac_secured,
+                bots_num,
+            },
+        ))
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct ExtraData {
     pub edf: u8,
-    #[nom(Cond = "edf & 0x80 != 0")]
     pub port: Option<i16>,
-    #[nom(Cond = "edf & 0x10 != 0")]
     pub server_steamid: Option<u64>,
-    #[nom(Cond = "edf & 0x40 != 0")]
     pub port_source_tv: Option<i16>,
-    #[nom(Cond = "edf & 0x40 != 0", Parse = "take_cstring")]
     pub name_source_tv: Option<CString>,
-    #[nom(Cond = "edf & 0x20 != 0", Parse = "take_cstring")]
     pub keywords: Option<CString>,
-    #[nom(Cond = "edf & 0x01 != 0")]
     pub gameid: Option<u64>,
 }
 
-#[derive(Debug, Nom)]
-#[nom(LittleEndian)]
+impl ExtraData {
+    pub fn parse(i: &[u8]) -> FieldResult<'_, Self> {
+        let (i, extra_data) = ExtraDataRef::parse(i)?;
+        Ok((i, extra_data.to_owned()))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TheShipInfo {
+    pub mode: u8,
+    pub witnesses: u8,
+    pub duration: u8,
+}
+
+impl TheShipInfo {
+    pub fn parse(i: &[u8]) -> FieldResult<'_, Self> {
+        let (i, mode) = field("mode", le_u8)(i)?;
+        let (i, witnesses) = field("witnesses", le_u8)(i)?;
+        let (i, duration) = field("duration", le_u8)(i)?;
+        Ok((
+            i,
+            Self {
+                mode,
+                witnesses,
+                duration,
+            },
+        ))
+    }
+}
+
+/// Parsed as `InfoNewRef`, which holds the grammar of the reply.
+#[derive(Debug)]
 pub struct InfoNew {
     pub protocol: u8,
-    #[nom(Parse = "take_cstring")]
     pub name: CString,
-    #[nom(Parse = "take_cstring")]
     pub map: CString,
-    #[nom(Parse = "take_cstring")]
     pub folder: CString,
-    #[nom(Parse = "take_cstring")]
     pub game: CString,
     pub steamid: i16,
     pub players: u8,
@@ -96,45 +181,85 @@ pub struct InfoNew {
     pub bots: u8,
     pub server_type: u8,
     pub enviroment: u8,
-    #[nom(Parse = "le_bool")]
     pub is_visible: bool,
-    #[nom(Parse = "le_bool")]
     pub vac_secured: bool,
-    #[nom(Parse = "take_cstring")]
+    pub the_ship: Option<TheShipInfo>,
     pub version: CString,
     pub extra_data: ExtraData,
 }
 
-#[derive(Debug, Nom)]
-#[nom(LittleEndian)]
+impl InfoNew {
+    pub fn parse(i: &[u8]) -> FieldResult<'_, Self> {
+        let (i, info) = InfoNewRef::parse(i)?;
+        Ok((i, info.to_owned()))
+    }
+}
+
+#[derive(Debug)]
 pub struct Player {
     pub index: u8,
-    #[nom(Parse = "take_cstring")]
     pub name: CString,
     pub score: i32,
-    #[nom(Parse = "le_f32", Map = "Duration::from_secs_f32")]
     pub duration: Duration,
+    pub the_ship: Option<TheShipPlayer>,
+}
+
+impl Player {
+    pub fn parse(i: &[u8]) -> FieldResult<'_, Self> {
+        let (i, player) = PlayerRef::parse(i)?;
+        Ok((i, player.to_owned()))
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct TheShipPlayer {
+    pub deaths: i32,
+    pub money: i32,
+}
+
+impl TheShipPlayer {
+    pub fn parse(i: &[u8]) -> FieldResult<'_, Self> {
+        let (i, deaths) = field("deaths", le_i32)(i)?;
+        let (i, money) = field("money", le_i32)(i)?;
+        Ok((i, Self { deaths, money }))
+    }
 }
 
-#[derive(Debug, Nom)]
-#[nom(LittleEndian)]
+#[derive(Debug)]
 pub struct PlayersList {
     pub players_num: u8,
     pub players: Vec<Player>,
 }
 
-#[derive(Debug, Nom)]
-#[nom(LittleEndian)]
+impl PlayersList {
+    pub fn parse(i: &[u8], app_id: i16) -> FieldResult<'_, Self> {
+        let (i, list) = PlayersListRef::parse(i, app_id)?;
+        Ok((i, list.to_owned()))
+    }
+}
+
+#[derive(Debug)]
 pub struct Rule {
-    #[nom(Parse = "take_cstring")]
     pub key: CString,
-    #[nom(Parse = "take_cstring")]
     pub value: CString,
 }
 
-#[derive(Debug, Nom)]
-#[nom(LittleEndian)]
+impl Rule {
+    pub fn parse(i: &[u8]) -> FieldResult<'_, Self> {
+        let (i, rule) = RuleRef::parse(i)?;
+        Ok((i, rule.to_owned()))
+    }
+}
+
+#[derive(Debug)]
 pub struct RulesList {
     pub rules_num: u16,
     pub rules: Vec<Rule>,
 }
+
+impl RulesList {
+    pub fn parse(i: &[u8]) -> FieldResult<'_, Self> {
+        let (i, list) = RulesListRef::parse(i)?;
+        Ok((i, list.to_owned()))
+    }
+}
@@ -0,0 +1,327 @@
+//! Variants of the A2S replies, which borrow strings from the reply instead of copying them.
+//! Owned replies are parsed as these and copied afterwards.
+use super::{
+    a2s::{le_bool, secs_f32, take_cstr},
+    ExtraData, InfoNew, Player, PlayersList, Rule, RulesList, TheShipInfo, TheShipPlayer,
+    THE_SHIP_APP_ID,
+};
+use crate::parse::{field, indexed, FieldError, FieldResult};
+use alloc::{borrow::ToOwned, vec::Vec};
+use core::{ffi::CStr, time::Duration};
+use nom::{
+    combinator::{complete, cond, map},
+    multi::many0,
+    number::streaming::{le_f32, le_i16, le_i32, le_u16, le_u64, le_u8},
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct ExtraDataRef<'a> {
+    pub edf: u8,
+    pub port: Option<i16>,
+    pub server_steamid: Option<u64>,
+    pub port_source_tv: Option<i16>,
+    pub name_source_tv: Option<&'a CStr>,
+    pub keywords: Option<&'a CStr>,
+    pub gameid: Option<u64>,
+}
+
+/// Extra data fields in the order they follow each other in a reply.
+const EDF_ORDER: [u8; 5] = [0x80, 0x10, 0x40, 0x20, 0x01];
+
+impl<'a> ExtraDataRef<'a> {
+    pub fn parse(i: &'a [u8]) -> FieldResult<'a, Self> {
+        let (i, edf) = field("edf", le_u8)(i)?;
+        match Self::parse_fields(i, edf) {
+            (i, extra_data, None) => Ok((i, extra_data)),
+            (_, _, Some(err)) => Err(err),
+        }
+    }
+
+    /// Parses the fields announced by `edf` in order up to the first one, which fails.
+    /// Flags of the failed field and of the fields after it are cleared in `edf` of the result.
+    pub(crate) fn parse_fields(
+        mut i: &'a [u8],
+        edf: u8,
+    ) -> (&'a [u8], Self, Option<nom::Err<FieldError<'a>>>) {
+        let mut extra_data = Self {
+            edf,
+            ..Self::default()
+        };
+        for (index, &flag) in EDF_ORDER.iter().enumerate() {
+            if edf & flag == 0 {
+                continue;
+            }
+            match extra_data.parse_field(i, flag) {
+                Ok(rest) => i = rest,
+                Err(err) => {
+                    EDF_ORDER[index..]
+                        .iter()
+                        .for_each(|flag| extra_data.edf &= !flag);
+                    return (i, extra_data, Some(err));
+                }
+            }
+        }
+        (i, extra_data, None)
+    }
+
+    fn parse_field(&mut self, i: &'a [u8], flag: u8) -> Result<&'a [u8], nom::Err<FieldError<'a>>> {
+        match flag {
+            0x80 => {
+                let (i, port) = field("port", le_i16)(i)?;
+                self.port = Some(port);
+                Ok(i)
+            }
+            0x10 => {
+                let (i, server_steamid) = field("server_steamid", le_u64)(i)?;
+                self.server_steamid = Some(server_steamid);
+                Ok(i)
+            }
+            0x40 => {
+                let (i, port_source_tv) = field("port_source_tv", le_i16)(i)?;
+                let (i, name_source_tv) = field("name_source_tv", take_cstr)(i)?;
+                self.port_source_tv = Some(port_source_tv);
+                self.name_source_tv = Some(name_source_tv);
+                Ok(i)
+            }
+            0x20 => {
+                let (i, keywords) = field("keywords", take_cstr)(i)?;
+                self.keywords = Some(keywords);
+                Ok(i)
+            }
+            _ => {
+                let (i, gameid) = field("gameid", le_u64)(i)?;
+                self.gameid = Some(gameid);
+                Ok(i)
+            }
+        }
+    }
+
+    pub fn to_owned(&self) -> ExtraData {
+        ExtraData {
+            edf: self.edf,
+            port: self.port,
+            server_steamid: self.server_steamid,
+            port_source_tv: self.port_source_tv,
+            name_source_tv: self.name_source_tv.map(CStr::to_owned),
+            keywords: self.keywords.map(CStr::to_owned),
+            gameid: self.gameid,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InfoNewRef<'a> {
+    pub protocol: u8,
+    pub name: &'a CStr,
+    pub map: &'a CStr,
+    pub folder: &'a CStr,
+    pub game: &'a CStr,
+    pub steamid: i16,
+    pub players: u8,
+    pub max_players: u8,
+    pub bots: u8,
+    pub server_type: u8,
+    pub enviroment: u8,
+    pub is_visible: bool,
+    pub vac_secured: bool,
+    pub the_ship: Option<TheShipInfo>,
+    pub version: &'a CStr,
+    pub extra_data: ExtraDataRef<'a>,
+}
+
+impl<'a> InfoNewRef<'a> {
+    pub fn parse(i: &'a [u8]) -> FieldResult<'a, Self> {
+        let (i, mut info) = Self::parse_head(i)?;
+        let (i, extra_data) = field("extra_data", ExtraDataRef::parse)(i)?;
+        info.extra_data = extra_data;
+        Ok((i, info))
+    }
+
+    /// Everything before the extra data flag, `extra_data` is left empty.
+    pub(crate) fn parse_head(i: &'a [u8]) -> FieldResult<'a, Self> {
+        let (i, protocol) = field("protocol", le_u8)(i)?;
+        let (i, name) = field("name", take_cstr)(i)?;
+        let (i, map) = field("map", take_cstr)(i)?;
+        let (i, folder) = field("folder", take_cstr)(i)?;
+        let (i, game) = field("game", take_cstr)(i)?;
+        let (i, steamid) = field("steamid", le_i16)(i)?;
+        let (i, players) = field("players", le_u8)(i)?;
+        let (i, max_players) = field("max_players", le_u8)(i)?;
+        let (i, bots) = field("bots", le_u8)(i)?;
+        let (i, server_type) = field("server_type", le_u8)(i)?;
+        let (i, enviroment) = field("enviroment", le_u8)(i)?;
+        let (i, is_visible) = field("is_visible", le_bool)(i)?;
+        let (i, vac_secured) = field("vac_secured", le_bool)(i)?;
+        let (i, the_ship) = cond(
+            steamid == THE_SHIP_APP_ID,
+            field("the_ship", TheShipInfo::parse),
+        )(i)?;
+        let (i, version) = field("version", take_cstr)(i)?;
+        Ok((
+            i,
+            Self {
+                protocol,
+                name,
+                map,
+                folder,
+                game,
+                steamid,
+                players,
+                max_players,
+                bots,
+                server_type,
+                enviroment,
+                is_visible,
+                vac_secured,
+                the_ship,
+                version,
+                extra_data: ExtraDataRef::default(),
+            },
+        ))
+    }
+
+    pub fn to_owned(&self) -> InfoNew {
+        InfoNew {
+            protocol: self.protocol,
+            name: self.name.to_owned(),
+            map: self.map.to_owned(),
+            folder: self.folder.to_owned(),
+            game: self.game.to_owned(),
+            steamid: self.steamid,
+            players: self.players,
+            max_players: self.max_players,
+            bots: self.bots,
+            server_type: self.server_type,
+            enviroment: self.enviroment,
+            is_visible: self.is_visible,
+            vac_secured: self.vac_secured,
+            the_ship: self.the_ship.clone(),
+            version: self.version.to_owned(),
+            extra_data: self.extra_data.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlayerRef<'a> {
+    pub index: u8,
+    pub name: &'a CStr,
+    pub score: i32,
+    pub duration: Duration,
+    pub the_ship: Option<TheShipPlayer>,
+}
+
+impl<'a> PlayerRef<'a> {
+    pub fn parse(i: &'a [u8]) -> FieldResult<'a, Self> {
+        let (i, index) = field("index", le_u8)(i)?;
+        let (i, name) = field("name", take_cstr)(i)?;
+        let (i, score) = field("score", le_i32)(i)?;
+        let (i, duration) = field("duration", map(le_f32, secs_f32))(i)?;
+        Ok((
+            i,
+            Self {
+                index,
+                name,
+                score,
+                duration,
+                the_ship: None,
+            },
+        ))
+    }
+
+    pub fn to_owned(&self) -> Player {
+        Player {
+            index: self.index,
+            name: self.name.to_owned(),
+            score: self.score,
+            duration: self.duration,
+            the_ship: self.the_ship.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlayersListRef<'a> {
+    pub players_num: u8,
+    pub players: Vec<PlayerRef<'a>>,
+}
+
+impl<'a> PlayersListRef<'a> {
+    pub fn parse(i: &'a [u8], app_id: i16) -> FieldResult<'a, Self> {
+        let (i, players_num) = field("players_num", le_u8)(i)?;
+        if app_id != THE_SHIP_APP_ID {
+            let (i, players) = many0(complete(PlayerRef::parse))(i)?;
+            return Ok((
+                i,
+                Self {
+                    players_num,
+                    players,
+                },
+            ));
+        }
+        // The Ship appends deaths and money of every player after the whole list
+        let count = players_num as usize;
+        let (i, mut players) = field("players", indexed(PlayerRef::parse, count))(i)?;
+        let (i, extensions) = field("the_ship", indexed(TheShipPlayer::parse, count))(i)?;
+        players
+            .iter_mut()
+            .zip(extensions)
+            .for_each(|(player, extension)| player.the_ship = Some(extension));
+        Ok((
+            i,
+            Self {
+                players_num,
+                players,
+            },
+        ))
+    }
+
+    pub fn to_owned(&self) -> PlayersList {
+        PlayersList {
+            players_num: self.players_num,
+            players: self.players.iter().map(PlayerRef::to_owned).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RuleRef<'a> {
+    pub key: &'a CStr,
+    pub value: &'a CStr,
+}
+
+impl<'a> RuleRef<'a> {
+    pub fn parse(i: &'a [u8]) -> FieldResult<'a, Self> {
+        let (i, key) = field("key", take_cstr)(i)?;
+        let (i, value) = field("value", take_cstr)(i)?;
+        Ok((i, Self { key, value }))
+    }
+
+    pub fn to_owned(&self) -> Rule {
+        Rule {
+            key: self.key.to_owned(),
+            value: self.value.to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RulesListRef<'a> {
+    pub rules_num: u16,
+    pub rules: Vec<RuleRef<'a>>,
+}
+
+impl<'a> RulesListRef<'a> {
+    pub fn parse(i: &'a [u8]) -> FieldResult<'a, Self> {
+        let (i, rules_num) = field("rules_num", le_u16)(i)?;
+        let (i, rules) = many0(complete(RuleRef::parse))(i)?;
+        Ok((i, Self { rules_num, rules }))
+    }
+
+    pub fn to_owned(&self) -> RulesList {
+        RulesList {
+            rules_num: self.rules_num,
+            rules: self.rules.iter().map(RuleRef::to_owned).collect(),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use super::{InfoNew, PacketError, PacketParser, PlayersList, QueryResult, RulesList, ValveQuery};
+use crate::RateLimiter;
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    marker::PhantomData,
+    net::SocketAddr,
+    sync::{Arc, Condvar, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+struct Entry<T> {
+    value: Option<(Arc<T>, Instant)>,
+    refreshing: bool,
+}
+
+/// Ends the refresh of `key` and wakes up waiters, even if the fetch panics.
+struct Refresh<'a, K: Eq + Hash, T> {
+    cache: &'a Cache<K, T>,
+    key: &'a K,
+}
+
+impl<K: Eq + Hash, T> Drop for Refresh<'_, K, T> {
+    fn drop(&mut self) {
+        let mut entries = self
+            .cache
+            .entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(entry) = entries.get_mut(self.key) {
+            entry.refreshing = false;
+        }
+        self.cache.fetched.notify_all();
+    }
+}
+
+/// Values fetched by key and kept for a time to live.
+///
+/// Concurrent requests of a missing value wait for a single fetch. Once a value expires,
+/// the first request refreshes it and the others get the stale value meanwhile.
+pub struct Cache<K, T> {
+    ttl: Duration,
+    entries: Mutex<HashMap<K, Entry<T>>>,
+    fetched: Condvar,
+}
+
+impl<K: Eq + Hash + Clone, T> Cache<K, T> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+            fetched: Condvar::new(),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Cached value of `key`, calling `fetch` if it's missing or expired.
+    ///
+    /// Failed fetch isn't cached: the stale value is kept, and requests waiting
+    /// for a missing value fetch it themselves.
+    pub fn get<E, F>(&self, key: &K, fetch: F) -> Result<Arc<T>, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        let mut entries = self.entries.lock().unwrap();
+        loop {
+            let entry = entries.entry(key.clone()).or_insert(Entry {
+                value: None,
+                refreshing: false,
+            });
+            match (&entry.value, entry.refreshing) {
+                (Some((value, fetched)), _) if fetched.elapsed() < self.ttl => {
+                    return Ok(Arc::clone(value))
+                }
+                (Some((value, _)), true) => return Ok(Arc::clone(value)),
+                (None, true) => entries = self.fetched.wait(entries).unwrap(),
+                (_, false) => break,
+            }
+        }
+        entries.get_mut(key).unwrap().refreshing = true;
+        drop(entries);
+
+        let refresh = Refresh { cache: self, key };
+        let result = fetch().map(Arc::new);
+        if let Ok(value) = &result {
+            let mut entries = self.entries.lock().unwrap();
+            if let Some(entry) = entries.get_mut(key) {
+                entry.value = Some((Arc::clone(value), Instant::now()));
+            }
+        }
+        drop(refresh);
+        result
+    }
+
+    pub fn invalidate(&self, key: &K) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.get_mut(key) {
+            entry.value = None;
+        }
+    }
+
+    /// Drops expired values, which aren't being refreshed.
+    pub fn purge(&self) {
+        let ttl = self.ttl;
+        self.entries.lock().unwrap().retain(|_, entry| {
+            entry.refreshing
+                || matches!(&entry.value, Some((_, fetched)) if fetched.elapsed() < ttl)
+        });
+    }
+}
+
+/// Caches replies to A2S_INFO, A2S_PLAYER and A2S_RULES by server's address.
+pub struct ServerCache<P: PacketParser> {
+    timeout: Duration,
+    limiter: Option<Arc<RateLimiter>>,
+    info: Cache<SocketAddr, InfoNew>,
+    players: Cache<SocketAddr, PlayersList>,
+    rules: Cache<SocketAddr, RulesList>,
+    _parser: PhantomData<fn() -> P>,
+}
+
+impl<P: PacketParser> ServerCache<P> {
+    pub fn new(ttl: Duration, timeout: Duration) -> Self {
+        Self {
+            timeout,
+            limiter: None,
+            info: Cache::new(ttl),
+            players: Cache::new(ttl),
+            rules: Cache::new(ttl),
+            _parser: PhantomData,
+        }
+    }
+
+    pub fn set_rate_limiter(&mut self, limiter: Option<Arc<RateLimiter>>) {
+        self.limiter = limiter;
+    }
+
+    fn query(&self, address: SocketAddr) -> QueryResult<ValveQuery<P>> {
+        let mut query = ValveQuery::connected(address, self.timeout).map_err(PacketError::from)?;
+        query.set_rate_limiter(self.limiter.clone());
+        Ok(query)
+    }
+
+    pub fn info(&self, address: SocketAddr) -> QueryResult<Arc<InfoNew>> {
+        self.info
+            .get(&address, || self.query(address)?.a2s_info_new())
+    }
+
+    /// Uses app id of the cached A2S_INFO reply, if any, to parse game-specific fields.
+    pub fn players(&self, address: SocketAddr) -> QueryResult<Arc<PlayersList>> {
+        self.players.get(&address, || {
+            let app_id = self.cached_app_id(address);
+            let query = self.query(address)?;
+            let challenge = query.a2s_player_challenge()?;
+            query.a2s_players_with_app_id(challenge, app_id)
+        })
+    }
+
+    pub fn rules(&self, address: SocketAddr) -> QueryResult<Arc<RulesList>> {
+        self.rules.get(&address, || {
+            let query = self.query(address)?;
+            let challenge = query.a2s_rules_challenge()?;
+            query.a2s_rules(challenge)
+        })
+    }
+
+    pub fn invalidate(&self, address: SocketAddr) {
+        self.info.invalidate(&address);
+        self.players.invalidate(&address);
+        self.rules.invalidate(&address);
+    }
+
+    pub fn purge(&self) {
+        self.info.purge();
+        self.players.purge();
+        self.rules.purge();
+    }
+
+    fn cached_app_id(&self, address: SocketAddr) -> i16 {
+        let entries = self.info.entries.lock().unwrap();
+        entries
+            .get(&address)
+            .and_then(|entry| entry.value.as_ref())
+            .map_or(0, |(info, _)| info.steamid)
+    }
+}
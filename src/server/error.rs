@@ -1,20 +1,32 @@
 use super::packet::error::Error as PacketError;
+use crate::{ErrorKind, ParseError};
 use thiserror::Error;
 
-type NomError<'a> = nom::Err<nom::error::Error<&'a [u8]>>;
-type NomErrorOwned = nom::Err<nom::error::Error<Vec<u8>>>;
-
 #[derive(Debug, Error)]
 pub enum Error {
     #[error(transparent)]
     Packet(#[from] PacketError),
     #[error(transparent)]
-    A2SParse(NomErrorOwned),
+    A2SParse(#[from] ParseError),
+    #[error("Server replied with a challenge ({0:#010x}) instead of data")]
+    ChallengeRequired(u32),
+    /// Reply is of another type, e.g. the obsolete A2S_INFO of goldsrc servers.
+    #[error("Expected reply header {expected:#04x}, got {found:?}")]
+    UnexpectedHeader { expected: u8, found: Option<u8> },
+    /// Header of the reply isn't one of the known A2S replies.
+    #[error("Unknown reply header {0:?}")]
+    UnknownReply(Option<u8>),
 }
 
-impl From<NomError<'_>> for Error {
-    fn from(error: NomError<'_>) -> Self {
-        Error::A2SParse(error.map(|e| nom::error::make_error(e.input.to_vec(), e.code)))
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Packet(err) => err.kind(),
+            Error::A2SParse(_) | Error::UnexpectedHeader { .. } | Error::UnknownReply(_) => {
+                ErrorKind::MalformedReply
+            }
+            Error::ChallengeRequired(_) => ErrorKind::ChallengeRequired,
+        }
     }
 }
 
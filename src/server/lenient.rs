@@ -0,0 +1,142 @@
+use super::{
+    ExtraData, ExtraDataRef, InfoNew, InfoNewRef, Player, PlayersList, RulesList, TheShipPlayer,
+    THE_SHIP_APP_ID,
+};
+use crate::parse::{field, FieldResult};
+use alloc::{vec, vec::Vec};
+use nom::{
+    combinator::complete,
+    multi::{many0, many_m_n},
+    number::streaming::le_u8,
+};
+
+/// Value parsed from a non-compliant reply along with irregularities found in it.
+#[derive(Debug)]
+pub struct Lenient<T> {
+    pub value: T,
+    pub warnings: Vec<Warning>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// Reply ends before the extra data flag.
+    MissingExtraData,
+    /// Reply ends before all of the fields announced by the extra data flag.
+    /// `ExtraData::edf` keeps flags of the parsed fields only.
+    TruncatedExtraData {
+        announced: u8,
+    },
+    /// Reply doesn't contain deaths and money of every The Ship's player.
+    MissingTheShipData,
+    PlayersCount {
+        announced: u8,
+        parsed: usize,
+    },
+    RulesCount {
+        announced: u16,
+        parsed: usize,
+    },
+    /// Unparsed bytes after the end of the reply, e.g. a truncated player.
+    TrailingBytes(usize),
+}
+
+fn check_trailing(rest: &[u8], warnings: &mut Vec<Warning>) {
+    if !rest.is_empty() {
+        warnings.push(Warning::TrailingBytes(rest.len()));
+    }
+}
+
+impl ExtraData {
+    /// Keeps the announced fields, which fit into the reply, up to the first one that doesn't.
+    fn parse_lenient(i: &[u8]) -> (&[u8], ExtraData, Option<Warning>) {
+        let (&announced, i) = match i.split_first() {
+            Some(split) => split,
+            None => return (i, ExtraData::default(), Some(Warning::MissingExtraData)),
+        };
+        let (rest, extra_data, err) = ExtraDataRef::parse_fields(i, announced);
+        let warning = err.map(|_| Warning::TruncatedExtraData { announced });
+        (rest, extra_data.to_owned(), warning)
+    }
+}
+
+impl InfoNew {
+    /// Same as `parse`, but tolerates missing or truncated extra data.
+    pub fn parse_lenient(i: &[u8]) -> FieldResult<'_, Lenient<InfoNew>> {
+        let (i, head) = InfoNewRef::parse_head(i)?;
+        let mut info = head.to_owned();
+        let (rest, extra_data, warning) = ExtraData::parse_lenient(i);
+        info.extra_data = extra_data;
+        let mut warnings: Vec<_> = warning.into_iter().collect();
+        check_trailing(rest, &mut warnings);
+        Ok((
+            rest,
+            Lenient {
+                value: info,
+                warnings,
+            },
+        ))
+    }
+}
+
+impl PlayersList {
+    /// Same as `parse`, but tolerates less players than announced, a truncated last player
+    /// and missing game-specific extensions.
+    pub fn parse_lenient(i: &[u8], app_id: i16) -> FieldResult<'_, Lenient<PlayersList>> {
+        let mut warnings = vec![];
+        let (i, players_num) = field("players_num", le_u8)(i)?;
+        let (rest, players) = if app_id == THE_SHIP_APP_ID {
+            let (i, mut players) = many_m_n(0, players_num as usize, complete(Player::parse))(i)?;
+            let (rest, extensions) = many_m_n(0, players.len(), complete(TheShipPlayer::parse))(i)?;
+            if extensions.len() < players.len() {
+                warnings.push(Warning::MissingTheShipData);
+            }
+            players
+                .iter_mut()
+                .zip(extensions)
+                .for_each(|(player, extension)| player.the_ship = Some(extension));
+            (rest, players)
+        } else {
+            many0(complete(Player::parse))(i)?
+        };
+
+        if players.len() != players_num as usize {
+            warnings.push(Warning::PlayersCount {
+                announced: players_num,
+                parsed: players.len(),
+            });
+        }
+        check_trailing(rest, &mut warnings);
+        Ok((
+            rest,
+            Lenient {
+                value: PlayersList {
+                    players_num,
+                    players,
+                },
+                warnings,
+            },
+        ))
+    }
+}
+
+impl RulesList {
+    /// Same as `parse`, but reports less rules than announced and a truncated last rule.
+    pub fn parse_lenient(i: &[u8]) -> FieldResult<'_, Lenient<RulesList>> {
+        let mut warnings = vec![];
+        let (rest, list) = RulesList::parse(i)?;
+        if list.rules.len() != list.rules_num as usize {
+            warnings.push(Warning::RulesCount {
+                announced: list.rules_num,
+                parsed: list.rules.len(),
+            });
+        }
+        check_trailing(rest, &mut warnings);
+        Ok((
+            rest,
+            Lenient {
+                value: list,
+                warnings,
+            },
+        ))
+    }
+}
@@ -1,9 +1,8 @@
+use crate::{ErrorKind, LimitExceeded, ParseError};
+#[cfg(feature = "std")]
 use bzip2::Error as Bz2Error;
 use thiserror::Error;
 
-type NomError<'a> = nom::Err<nom::error::Error<&'a [u8]>>;
-type NomErrorOwned = nom::Err<nom::error::Error<Vec<u8>>>;
-
 #[derive(Debug)]
 pub struct MultiHeader {
     pub uid: u32,
@@ -12,26 +11,47 @@ pub struct MultiHeader {
 
 #[derive(Error, Debug)]
 pub enum Error {
+    #[cfg(feature = "std")]
     #[error(transparent)]
     Io(#[from] std::io::Error),
     #[error(transparent)]
-    Nom(NomErrorOwned),
+    Parse(#[from] ParseError),
     #[error("Expected -2, but found {0}")]
     WrongHeader(i32),
+    #[error("Packet index {index} is out of {total} packets")]
+    WrongIndex { index: usize, total: usize },
     #[error("Mismatched packet headers: expected {base:?}, found {wrong:?}")]
     Interrupted {
         base: MultiHeader,
         wrong: MultiHeader,
     },
+    #[cfg(feature = "std")]
     #[error(transparent)]
     Decompress(#[from] Bz2Error),
+    /// Only returned without the `std` feature, which brings bzip2 in.
+    #[error("Compressed replies can't be decompressed without the std feature")]
+    CompressionUnsupported,
     #[error("Wrong crc32 of decompressed data: expected {0}, found {1}")]
     Crc32(u32, u32),
+    #[error(transparent)]
+    Limit(#[from] LimitExceeded),
 }
 
-impl From<NomError<'_>> for Error {
-    fn from(error: NomError<'_>) -> Self {
-        Error::Nom(error.map(|e| nom::error::make_error(e.input.to_vec(), e.code)))
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(err) => ErrorKind::from_io(err),
+            Error::Parse(_) | Error::WrongHeader(_) | Error::WrongIndex { .. } => {
+                ErrorKind::MalformedReply
+            }
+            Error::Interrupted { .. } => ErrorKind::Interrupted,
+            #[cfg(feature = "std")]
+            Error::Decompress(_) => ErrorKind::Decompression,
+            Error::CompressionUnsupported => ErrorKind::Decompression,
+            Error::Crc32(..) => ErrorKind::Checksum,
+            Error::Limit(_) => ErrorKind::LimitExceeded,
+        }
     }
 }
 
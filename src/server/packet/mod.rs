@@ -1,12 +1,23 @@
-use bzip2::{Decompress, Error as Bz2Error};
+use crate::{
+    parse::{field, FieldResult, ParseError},
+    LimitExceeded, LimitKind, Limits,
+};
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "std")]
+use bzip2::{Decompress, Error as Bz2Error, Status};
+use core::marker::PhantomData;
 use crc::crc32::checksum_ieee;
-use nom_derive::Nom;
-use std::{io::Result as IOResult, net::UdpSocket};
+use nom::{
+    combinator::cond,
+    number::streaming::{le_u16, le_u32, le_u8},
+};
 
 pub mod error;
 use error::{Error as PacketError, MultiHeader, PacketResult};
 
-const DEFAULT_PACKET_SIZE: usize = 1400;
+pub(crate) const DEFAULT_PACKET_SIZE: usize = 1400;
+#[cfg(feature = "std")]
+const DECOMPRESS_CHUNK: usize = 64 * 1024;
 
 struct DecompressInfo {
     decompressed_size: u32,
@@ -23,15 +34,15 @@ pub struct MultiPacket {
 }
 
 pub trait PacketParser {
-    fn parse(i: &[u8]) -> nom::IResult<&[u8], MultiPacket>;
+    fn parse(i: &[u8]) -> FieldResult<'_, MultiPacket>;
 }
 
 pub struct GoldsrcParser;
 
 impl PacketParser for GoldsrcParser {
-    fn parse(i: &[u8]) -> nom::IResult<&[u8], MultiPacket> {
-        let (i, uid) = nom::number::streaming::le_u32(i)?;
-        let (i, num) = nom::number::streaming::le_u8(i)?;
+    fn parse(i: &[u8]) -> FieldResult<'_, MultiPacket> {
+        let (i, uid) = field("uid", le_u32)(i)?;
+        let (i, num) = field("number", le_u8)(i)?;
         Ok((
             &[],
             MultiPacket {
@@ -49,29 +60,24 @@ impl PacketParser for GoldsrcParser {
 pub struct SourceParser;
 
 impl PacketParser for SourceParser {
-    fn parse(i: &[u8]) -> nom::IResult<&[u8], MultiPacket> {
-        #[derive(Nom)]
-        #[nom(LittleEndian)]
-        struct SourcePacket {
-            uid: u32,
-            total: u8,
-            index: u8,
-            size: u16,
-            #[nom(Cond = "uid & 0x80000000 != 0")]
-            decomp_data: Option<u32>,
-            #[nom(Cond = "uid & 0x80000000 != 0")]
-            crc32: Option<u32>,
-        }
-        let (i, packet) = SourcePacket::parse(i)?;
+    fn parse(i: &[u8]) -> FieldResult<'_, MultiPacket> {
+        let (i, uid) = field("uid", le_u32)(i)?;
+        let (i, total) = field("total", le_u8)(i)?;
+        let (i, index) = field("index", le_u8)(i)?;
+        let (i, size) = field("size", le_u16)(i)?;
+        // Only the first packet of a compressed reply has these fields
+        let compressed = uid & 0x8000_0000 != 0 && index == 0;
+        let (i, decompressed_size) = cond(compressed, field("decompressed_size", le_u32))(i)?;
+        let (i, crc32) = cond(compressed, field("crc32", le_u32))(i)?;
         Ok((
             &[],
             MultiPacket {
-                uid: packet.uid,
-                index: packet.index as usize,
-                total: packet.total as usize,
-                switch_size: packet.size as usize,
+                uid,
+                index: index as usize,
+                total: total as usize,
+                switch_size: size as usize,
                 decompress_info: if let (Some(decompressed_size), Some(crc32_sum)) =
-                    (packet.decomp_data, packet.crc32)
+                    (decompressed_size, crc32)
                 {
                     Some(DecompressInfo {
                         crc32_sum,
@@ -86,67 +92,224 @@ impl PacketParser for SourceParser {
     }
 }
 
-fn read_raw(socket: &UdpSocket, packet_size: usize) -> IOResult<Vec<u8>> {
+#[cfg(feature = "std")]
+fn read_raw<T: crate::Transport>(socket: &T, packet_size: usize) -> std::io::Result<Vec<u8>> {
     let mut buf = vec![0; packet_size];
     let size = socket.recv(&mut buf)?;
     buf.truncate(size);
     Ok(buf)
 }
 
+/// Output grows with the decompressed data instead of being allocated from the header upfront.
+/// `output_size` must be capped by the caller.
+#[cfg(feature = "std")]
 fn decompress(compressed: &[u8], output_size: usize) -> Result<Vec<u8>, Bz2Error> {
-    let mut decompressed = vec![0; output_size];
-    let mut decompressor = Decompress::new(false); // Packets won't be large anyway, so don't worry about memmory
-    decompressor.decompress(compressed, &mut decompressed)?;
+    let mut decompressed = Vec::new();
+    let mut decompressor = Decompress::new(false);
+    while decompressed.len() < output_size {
+        decompressed.reserve_exact((output_size - decompressed.len()).min(DECOMPRESS_CHUNK));
+        let (total_in, total_out) = (decompressor.total_in(), decompressor.total_out());
+        let input = &compressed[(total_in as usize).min(compressed.len())..];
+        if decompressor.decompress_vec(input, &mut decompressed)? == Status::StreamEnd {
+            break;
+        }
+        if (total_in, total_out) == (decompressor.total_in(), decompressor.total_out()) {
+            // Input is over before the end of the stream
+            break;
+        }
+    }
     Ok(decompressed)
 }
 
-fn read_multi<P: PacketParser>(i: &[u8], socket: &UdpSocket) -> PacketResult<Vec<u8>> {
-    let (_, init_packet) = P::parse(i)?;
+#[cfg(not(feature = "std"))]
+fn decompress(_compressed: &[u8], _output_size: usize) -> PacketResult<Vec<u8>> {
+    Err(PacketError::CompressionUnsupported)
+}
+
+fn parse_header(packet: &[u8]) -> PacketResult<(&[u8], i32)> {
+    field("header", nom::number::complete::le_i32)(packet)
+        .map_err(|err| ParseError::new("packet", packet, err).into())
+}
+
+fn parse_multi<P: PacketParser>(i: &[u8]) -> PacketResult<MultiPacket> {
+    let (_, packet) = P::parse(i).map_err(|err| ParseError::new("multi-packet", i, err))?;
+    Ok(packet)
+}
+
+/// Result of feeding a datagram to `Reassembler`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Progress {
+    NeedMore,
+    Complete(Vec<u8>),
+}
+
+struct Pending {
+    uid: u32,
+    total: usize,
+    switch_size: usize,
+    decompress_info: Option<DecompressInfo>,
+    payloads: Vec<Option<Vec<u8>>>,
+    received: usize,
+    size: usize,
+}
+
+/// State machine, which turns received datagrams into a reply payload without doing any IO.
+pub struct Reassembler<P: PacketParser> {
+    pending: Option<Pending>,
+    limits: Limits,
+    packet_size: usize,
+    _parser: PhantomData<fn() -> P>,
+}
+
+impl<P: PacketParser> Default for Reassembler<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: PacketParser> Reassembler<P> {
+    pub fn new() -> Self {
+        Self::with_limits(Limits::default())
+    }
 
-    let mut payloads: Vec<Vec<u8>> = vec![vec![]; init_packet.total];
-    payloads.insert(init_packet.index, init_packet.payload);
-    while payloads.len() < payloads.capacity() {
-        let packet = read_raw(socket, init_packet.switch_size)?;
-        let (i, header) = nom::number::complete::le_i32(packet.as_ref())?;
-        if header != -2 {
-            return Err(PacketError::WrongHeader(header));
+    pub fn with_limits(limits: Limits) -> Self {
+        Self {
+            pending: None,
+            limits,
+            packet_size: DEFAULT_PACKET_SIZE,
+            _parser: PhantomData,
         }
-        let (_, new_packet) = P::parse(i)?;
-        if init_packet.uid != new_packet.uid || init_packet.total != new_packet.total {
+    }
+
+    /// Size of a buffer for datagrams, which don't announce their size, 1400 by default.
+    pub fn set_packet_size(&mut self, packet_size: usize) {
+        self.packet_size = packet_size;
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Size of a buffer, which fits the next datagram.
+    ///
+    /// It's one byte more than `Limits::fragment_size` at most, so too large datagrams
+    /// are detected instead of being silently truncated.
+    pub fn datagram_size(&self) -> usize {
+        self.pending
+            .as_ref()
+            .map_or(self.packet_size, |pending| {
+                pending.switch_size.max(self.packet_size)
+            })
+            .min(self.limits.fragment_size.saturating_add(1))
+    }
+
+    /// Drops parts of an incomplete reply.
+    pub fn reset(&mut self) {
+        self.pending = None;
+    }
+
+    /// Handles a received datagram. An error drops parts of an incomplete reply.
+    pub fn feed(&mut self, datagram: &[u8]) -> PacketResult<Progress> {
+        let progress = self.try_feed(datagram);
+        if !matches!(progress, Ok(Progress::NeedMore)) {
+            self.pending = None;
+        }
+        progress
+    }
+
+    fn try_feed(&mut self, datagram: &[u8]) -> PacketResult<Progress> {
+        let limits = self.limits;
+        LimitExceeded::check(
+            LimitKind::FragmentSize,
+            limits.fragment_size,
+            datagram.len(),
+        )?;
+        let (i, header) = parse_header(datagram)?;
+        match (header, &self.pending) {
+            (-1, None) => return Ok(Progress::Complete(i.to_vec())),
+            (-2, _) => {}
+            _ => return Err(PacketError::WrongHeader(header)),
+        }
+
+        let packet = parse_multi::<P>(i)?;
+        if packet.index >= packet.total {
+            return Err(PacketError::WrongIndex {
+                index: packet.index,
+                total: packet.total,
+            });
+        }
+        LimitExceeded::check(LimitKind::Fragments, limits.fragments, packet.total)?;
+        let pending = self.pending.get_or_insert_with(|| Pending {
+            uid: packet.uid,
+            total: packet.total,
+            switch_size: packet.switch_size,
+            decompress_info: None,
+            payloads: vec![None; packet.total],
+            received: 0,
+            size: 0,
+        });
+        if pending.uid != packet.uid || pending.total != packet.total {
             return Err(PacketError::Interrupted {
                 base: MultiHeader {
-                    uid: init_packet.uid,
-                    total: init_packet.total,
+                    uid: pending.uid,
+                    total: pending.total,
                 },
                 wrong: MultiHeader {
-                    uid: new_packet.uid,
-                    total: new_packet.total,
+                    uid: packet.uid,
+                    total: packet.total,
                 },
             });
         }
-        payloads.insert(new_packet.index as usize, new_packet.payload);
-    }
+        if packet.decompress_info.is_some() {
+            pending.decompress_info = packet.decompress_info;
+        }
+        let slot = &mut pending.payloads[packet.index];
+        if slot.is_none() {
+            // Duplicated datagrams are ignored
+            pending.size += packet.payload.len();
+            LimitExceeded::check(LimitKind::Payload, limits.payload, pending.size)?;
+            *slot = Some(packet.payload);
+            pending.received += 1;
+        }
+        if pending.received < pending.total {
+            return Ok(Progress::NeedMore);
+        }
 
-    let full_payload: Vec<u8> = payloads.into_iter().flatten().collect();
-    if let Some(decompress_info) = init_packet.decompress_info {
-        let full_payload = decompress(&full_payload, decompress_info.decompressed_size as usize)?;
-        let expected_crc32 = decompress_info.crc32_sum;
-        let calculated_crc32 = checksum_ieee(&full_payload);
-        if expected_crc32 != calculated_crc32 {
-            return Err(PacketError::Crc32(expected_crc32, calculated_crc32));
+        let pending = self.pending.take().unwrap();
+        let full_payload: Vec<u8> = pending.payloads.into_iter().flatten().flatten().collect();
+        if let Some(decompress_info) = pending.decompress_info {
+            // The announced size may be a lie, so the limit is checked against the actual output
+            let output_size = (decompress_info.decompressed_size as usize)
+                .min(limits.decompressed.saturating_add(1));
+            let full_payload = decompress(&full_payload, output_size)?;
+            LimitExceeded::check(
+                LimitKind::Decompressed,
+                limits.decompressed,
+                full_payload.len(),
+            )?;
+            let expected_crc32 = decompress_info.crc32_sum;
+            let calculated_crc32 = checksum_ieee(&full_payload);
+            if expected_crc32 != calculated_crc32 {
+                return Err(PacketError::Crc32(expected_crc32, calculated_crc32));
+            }
+            return Ok(Progress::Complete(full_payload));
         }
-        return Ok(full_payload);
+        Ok(Progress::Complete(full_payload))
     }
-
-    Ok(full_payload)
 }
 
-pub(crate) fn read_payload<P: PacketParser>(socket: &UdpSocket) -> PacketResult<Vec<u8>> {
-    let packet = read_raw(socket, DEFAULT_PACKET_SIZE)?;
-    let (i, header) = nom::number::complete::le_i32(packet.as_ref())?;
-    match header {
-        -1 => Ok(i.to_vec()),
-        -2 => read_multi::<P>(i, socket),
-        _ => Err(PacketError::WrongHeader(header)),
+#[cfg(feature = "std")]
+pub(crate) fn read_payload<P: PacketParser, T: crate::Transport>(
+    socket: &T,
+    limits: Limits,
+    packet_size: usize,
+) -> PacketResult<Vec<u8>> {
+    let mut reassembler = Reassembler::<P>::with_limits(limits);
+    reassembler.set_packet_size(packet_size);
+    loop {
+        let datagram = read_raw(socket, reassembler.datagram_size())?;
+        if let Progress::Complete(payload) = reassembler.feed(&datagram)? {
+            return Ok(payload);
+        }
     }
 }
@@ -0,0 +1,147 @@
+//! Requests and replies of the A2S protocol without any IO. Replies are decoded from payloads
+//! put together by `Reassembler`.
+use super::{
+    Error, InfoNew, InfoNewRef, InfoOld, Lenient, PlayersList, PlayersListRef, QueryResult,
+    RulesList, RulesListRef,
+};
+use crate::parse::{field, FieldResult, ParseError};
+use nom::{bytes::streaming::tag, number::streaming::le_u32, sequence::preceded};
+
+pub const INFO_REQUEST: &[u8] = b"\xFF\xFF\xFF\xFFTSource Engine Query\x00";
+
+/// Challenge sent to get a real one from the server.
+pub const NO_CHALLENGE: u32 = 0xFFFF_FFFF;
+
+fn challenge_request(header: u8, challenge: u32) -> [u8; 9] {
+    let challenge = challenge.to_le_bytes();
+    [
+        0xFF,
+        0xFF,
+        0xFF,
+        0xFF,
+        header,
+        challenge[0],
+        challenge[1],
+        challenge[2],
+        challenge[3],
+    ]
+}
+
+pub fn players_request(challenge: u32) -> [u8; 9] {
+    challenge_request(b'U', challenge)
+}
+
+pub fn rules_request(challenge: u32) -> [u8; 9] {
+    challenge_request(b'V', challenge)
+}
+
+fn parse_challenge(i: &[u8]) -> FieldResult<'_, u32> {
+    preceded(field("header", tag(b"A")), field("challenge", le_u32))(i)
+}
+
+fn strip_rules_prefix(answer: &[u8]) -> &[u8] {
+    if let Ok((i, four_ff)) = nom::number::complete::le_u32::<_, (_, nom::error::ErrorKind)>(answer)
+    {
+        if four_ff == 0xFFFF_FFFF {
+            // Undocumented: a2s_rules may start with four FF before header 0x45 (it's not single packet marker)
+            return i;
+        }
+    }
+    answer
+}
+
+/// Turns an unexpected challenge reply into `Error::ChallengeRequired`.
+fn check_challenge(answer: &[u8]) -> QueryResult<()> {
+    match parse_challenge(answer) {
+        Ok((_, challenge)) => Err(Error::ChallengeRequired(challenge)),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Turns a reply of another type into `Error::UnexpectedHeader`.
+fn check_header(answer: &[u8], expected: u8) -> QueryResult<()> {
+    match answer.first() {
+        Some(&header) if header == expected => Ok(()),
+        found => Err(Error::UnexpectedHeader {
+            expected,
+            found: found.copied(),
+        }),
+    }
+}
+
+/// Parses everything after the `header` byte of a reply.
+fn decode<'a, O>(
+    message: &'static str,
+    answer: &'a [u8],
+    header: u8,
+    parser: impl FnOnce(&'a [u8]) -> FieldResult<'a, O>,
+) -> QueryResult<O> {
+    check_challenge(answer)?;
+    check_header(answer, header)?;
+    let (_, value) = parser(&answer[1..]).map_err(|err| ParseError::new(message, answer, err))?;
+    Ok(value)
+}
+
+pub fn decode_challenge(answer: &[u8]) -> QueryResult<u32> {
+    let (_, challenge) =
+        parse_challenge(answer).map_err(|err| ParseError::new("A2S_CHALLENGE", answer, err))?;
+    Ok(challenge)
+}
+
+pub fn decode_info_old(answer: &[u8]) -> QueryResult<InfoOld> {
+    decode("A2S_INFO", answer, b'm', InfoOld::parse)
+}
+
+pub fn decode_info_new(answer: &[u8]) -> QueryResult<InfoNew> {
+    decode_info_new_ref(answer).map(|info| info.to_owned())
+}
+
+/// `app_id` is `InfoNew::steamid` of the server, used to parse game-specific extensions.
+pub fn decode_players(answer: &[u8], app_id: i16) -> QueryResult<PlayersList> {
+    decode_players_ref(answer, app_id).map(|list| list.to_owned())
+}
+
+pub fn decode_rules(answer: &[u8]) -> QueryResult<RulesList> {
+    decode_rules_ref(answer).map(|list| list.to_owned())
+}
+
+/// Same as `decode_info_new`, but borrows strings from `answer`.
+pub fn decode_info_new_ref(answer: &[u8]) -> QueryResult<InfoNewRef<'_>> {
+    decode("A2S_INFO", answer, b'I', InfoNewRef::parse)
+}
+
+/// Same as `decode_players`, but borrows names from `answer`.
+pub fn decode_players_ref(answer: &[u8], app_id: i16) -> QueryResult<PlayersListRef<'_>> {
+    decode("A2S_PLAYER", answer, b'D', |i| {
+        PlayersListRef::parse(i, app_id)
+    })
+}
+
+/// Same as `decode_rules`, but borrows keys and values from `answer`.
+pub fn decode_rules_ref(answer: &[u8]) -> QueryResult<RulesListRef<'_>> {
+    decode(
+        "A2S_RULES",
+        strip_rules_prefix(answer),
+        b'E',
+        RulesListRef::parse,
+    )
+}
+
+pub fn decode_info_new_lenient(answer: &[u8]) -> QueryResult<Lenient<InfoNew>> {
+    decode("A2S_INFO", answer, b'I', InfoNew::parse_lenient)
+}
+
+pub fn decode_players_lenient(answer: &[u8], app_id: i16) -> QueryResult<Lenient<PlayersList>> {
+    decode("A2S_PLAYER", answer, b'D', |i| {
+        PlayersList::parse_lenient(i, app_id)
+    })
+}
+
+pub fn decode_rules_lenient(answer: &[u8]) -> QueryResult<Lenient<RulesList>> {
+    decode(
+        "A2S_RULES",
+        strip_rules_prefix(answer),
+        b'E',
+        RulesList::parse_lenient,
+    )
+}
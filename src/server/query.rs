@@ -0,0 +1,178 @@
+use super::{
+    decode_challenge, decode_info_new, decode_info_new_lenient, decode_info_old, decode_players,
+    decode_players_lenient, decode_rules, decode_rules_lenient,
+    packet::{self, read_payload, DEFAULT_PACKET_SIZE},
+    players_request, rules_request, InfoNew, InfoOld, Lenient, PacketError, PacketParser,
+    PlayersList, QueryResult, RulesList, INFO_REQUEST, NO_CHALLENGE,
+};
+use crate::{transport::Failover, ErrorKind, Limits, RateLimiter, Transport};
+use std::{
+    io::Result as IOResult,
+    marker::PhantomData,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+pub struct ValveQuery<P: PacketParser, T: Transport = UdpSocket> {
+    transport: T,
+    limiter: Option<Arc<RateLimiter>>,
+    limits: Limits,
+    packet_size: usize,
+    failover: Mutex<Failover>,
+    _parser: PhantomData<P>,
+}
+
+impl<P: PacketParser> ValveQuery<P> {
+    pub fn bind(addr: SocketAddr) -> IOResult<Self> {
+        Ok(Self::with_transport(UdpSocket::bind(addr)?))
+    }
+
+    /// Connects to the first resolved address of `addrs`. If a request times out,
+    /// it's repeated with the next one, which is used afterwards if it replies.
+    pub fn connect<A: ToSocketAddrs>(&self, addrs: A) -> IOResult<()> {
+        *self.failover.lock().unwrap() = Failover::connect(&self.transport, addrs)?;
+        Ok(())
+    }
+
+    /// Binds to any local port and connects to `addr`.
+    pub(crate) fn connected(addr: SocketAddr, timeout: Duration) -> IOResult<Self> {
+        let local = match addr {
+            SocketAddr::V4(_) => "0.0.0.0:0",
+            SocketAddr::V6(_) => "[::]:0",
+        };
+        let query = Self::bind(local.parse().unwrap())?;
+        query.set_timeout(Some(timeout))?;
+        query.connect(addr)?;
+        Ok(query)
+    }
+
+    pub fn timeout(&self) -> IOResult<Option<Duration>> {
+        self.transport.read_timeout()
+    }
+
+    pub fn set_timeout(&self, timeout: Option<Duration>) -> IOResult<()> {
+        self.transport.set_read_timeout(timeout)
+    }
+}
+
+impl<P: PacketParser, T: Transport> ValveQuery<P, T> {
+    /// Query over another transport than UDP socket, e.g. `Replay` of recorded datagrams.
+    pub fn with_transport(transport: T) -> Self {
+        Self {
+            transport,
+            limiter: None,
+            limits: Limits::default(),
+            packet_size: DEFAULT_PACKET_SIZE,
+            failover: Mutex::default(),
+            _parser: PhantomData,
+        }
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn set_rate_limiter(&mut self, limiter: Option<Arc<RateLimiter>>) {
+        self.limiter = limiter;
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn packet_size(&self) -> usize {
+        self.packet_size
+    }
+
+    /// Size of a buffer for the first datagram of a reply, 1400 by default.
+    pub fn set_packet_size(&mut self, packet_size: usize) {
+        self.packet_size = packet_size;
+    }
+
+    fn request(&self, buf: &[u8]) -> QueryResult<Vec<u8>> {
+        // Failover is only locked around its updates, so requests don't wait for each other
+        let mut attempts = self.failover.lock().unwrap().attempts();
+        loop {
+            let current = self.failover.lock().unwrap().current();
+            match self.request_once(buf) {
+                Err(err) if err.kind() == ErrorKind::Timeout && attempts > 1 => {
+                    self.failover
+                        .lock()
+                        .unwrap()
+                        .advance(&self.transport, current)
+                        .map_err(PacketError::from)?;
+                    attempts -= 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn request_once(&self, buf: &[u8]) -> QueryResult<Vec<u8>> {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire(self.transport.peer_addr().map_err(PacketError::from)?);
+        }
+        self.transport
+            .send(buf)
+            .map_err(packet::error::Error::from)?;
+        Ok(read_payload::<P, T>(
+            &self.transport,
+            self.limits,
+            self.packet_size,
+        )?)
+    }
+
+    pub fn a2s_player_challenge(&self) -> QueryResult<u32> {
+        decode_challenge(&self.request(&players_request(NO_CHALLENGE))?)
+    }
+
+    pub fn a2s_rules_challenge(&self) -> QueryResult<u32> {
+        decode_challenge(&self.request(&rules_request(NO_CHALLENGE))?)
+    }
+
+    pub fn a2s_info_old(&self) -> QueryResult<InfoOld> {
+        decode_info_old(&self.request(INFO_REQUEST)?)
+    }
+
+    pub fn a2s_info_new(&self) -> QueryResult<InfoNew> {
+        decode_info_new(&self.request(INFO_REQUEST)?)
+    }
+
+    pub fn a2s_players(&self, challenge: u32) -> QueryResult<PlayersList> {
+        self.a2s_players_with_app_id(challenge, 0)
+    }
+
+    /// Same as `a2s_players`, but also parses game-specific extensions for `app_id`
+    /// (i.e. `InfoNew::steamid`), like deaths and money of The Ship's players.
+    pub fn a2s_players_with_app_id(&self, challenge: u32, app_id: i16) -> QueryResult<PlayersList> {
+        decode_players(&self.request(&players_request(challenge))?, app_id)
+    }
+
+    pub fn a2s_rules(&self, challenge: u32) -> QueryResult<RulesList> {
+        decode_rules(&self.request(&rules_request(challenge))?)
+    }
+
+    /// Same as `a2s_info_new`, but tolerates missing or truncated extra data.
+    pub fn a2s_info_new_lenient(&self) -> QueryResult<Lenient<InfoNew>> {
+        decode_info_new_lenient(&self.request(INFO_REQUEST)?)
+    }
+
+    /// Same as `a2s_players_with_app_id`, but tolerates incomplete lists of players.
+    pub fn a2s_players_lenient(
+        &self,
+        challenge: u32,
+        app_id: i16,
+    ) -> QueryResult<Lenient<PlayersList>> {
+        decode_players_lenient(&self.request(&players_request(challenge))?, app_id)
+    }
+
+    /// Same as `a2s_rules`, but reports incomplete lists of rules.
+    pub fn a2s_rules_lenient(&self, challenge: u32) -> QueryResult<Lenient<RulesList>> {
+        decode_rules_lenient(&self.request(&rules_request(challenge))?)
+    }
+}
@@ -0,0 +1,159 @@
+use super::{a2s::secs_f32, RulesList};
+use std::{collections::HashMap, time::Duration};
+
+/// Key-value view of `RulesList` with typed access to values.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Rules(HashMap<String, String>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuleChange {
+    Added {
+        key: String,
+        value: String,
+    },
+    Removed {
+        key: String,
+        value: String,
+    },
+    Changed {
+        key: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl RuleChange {
+    pub fn key(&self) -> &str {
+        match self {
+            RuleChange::Added { key, .. }
+            | RuleChange::Removed { key, .. }
+            | RuleChange::Changed { key, .. } => key,
+        }
+    }
+}
+
+impl From<&RulesList> for Rules {
+    fn from(list: &RulesList) -> Self {
+        Self(
+            list.rules
+                .iter()
+                .map(|rule| {
+                    (
+                        rule.key.to_string_lossy().into_owned(),
+                        rule.value.to_string_lossy().into_owned(),
+                    )
+                })
+                .collect(),
+        )
+    }
+}
+
+impl From<RulesList> for Rules {
+    fn from(list: RulesList) -> Self {
+        Self::from(&list)
+    }
+}
+
+impl Rules {
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Accepts both numeric (`0`, `1`) and textual (`false`, `true`) flags.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        let value = self.get(key)?.trim();
+        match value {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => value.parse::<f32>().ok().map(|num| num != 0.0),
+        }
+    }
+
+    pub fn get_i64(&self, key: &str) -> Option<i64> {
+        self.get(key)?.trim().parse().ok()
+    }
+
+    pub fn get_f32(&self, key: &str) -> Option<f32> {
+        self.get(key)?.trim().parse().ok()
+    }
+
+    pub fn gravity(&self) -> Option<f32> {
+        self.get_f32("sv_gravity")
+    }
+
+    /// `mp_timelimit` is measured in minutes, zero means there's no limit.
+    pub fn time_limit(&self) -> Option<Duration> {
+        self.get_f32("mp_timelimit")
+            .filter(|&minutes| minutes > 0.0)
+            .map(|minutes| secs_f32(minutes * 60.0))
+    }
+
+    /// Servers never expose the password itself, only whether it's set.
+    pub fn has_password(&self) -> Option<bool> {
+        self.get_bool("sv_password")
+    }
+
+    pub fn friendly_fire(&self) -> Option<bool> {
+        self.get_bool("mp_friendlyfire")
+    }
+
+    /// Names of enabled `tf_gamemode_*` flags, e.g. `ctf` or `payload`.
+    pub fn tf_game_modes(&self) -> Vec<&str> {
+        let mut modes: Vec<_> = self
+            .0
+            .keys()
+            .filter_map(|key| {
+                key.strip_prefix("tf_gamemode_")
+                    .filter(|_| self.get_bool(key) == Some(true))
+            })
+            .collect();
+        modes.sort_unstable();
+        modes
+    }
+
+    /// Changes that turn `self` into `newer`, sorted by rule key.
+    pub fn diff(&self, newer: &Rules) -> Vec<RuleChange> {
+        let mut changes: Vec<_> = self
+            .0
+            .iter()
+            .filter_map(|(key, old)| match newer.0.get(key) {
+                None => Some(RuleChange::Removed {
+                    key: key.clone(),
+                    value: old.clone(),
+                }),
+                Some(new) if new != old => Some(RuleChange::Changed {
+                    key: key.clone(),
+                    old: old.clone(),
+                    new: new.clone(),
+                }),
+                Some(_) => None,
+            })
+            .chain(
+                newer
+                    .0
+                    .iter()
+                    .filter(|(key, _)| !self.0.contains_key(*key))
+                    .map(|(key, value)| RuleChange::Added {
+                        key: key.clone(),
+                        value: value.clone(),
+                    }),
+            )
+            .collect();
+        changes.sort_by(|a, b| a.key().cmp(b.key()));
+        changes
+    }
+}
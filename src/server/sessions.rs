@@ -0,0 +1,161 @@
+use super::PlayersList;
+use std::time::{Duration, SystemTime};
+
+/// Continuous stay of a player on a server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    /// Synthetic id, unique within a tracker.
+    pub id: u64,
+    pub name: String,
+    pub score: i32,
+    /// Time of joining estimated from the player's duration.
+    pub joined: SystemTime,
+    pub last_seen: SystemTime,
+    /// Time of the first snapshot without the player.
+    pub left: Option<SystemTime>,
+    duration: Duration,
+}
+
+impl Session {
+    /// Time spent on the server until the player was seen for the last time.
+    pub fn playtime(&self) -> Duration {
+        self.last_seen
+            .duration_since(self.joined)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+    Joined(Session),
+    Left(Session),
+}
+
+/// Correlates successive `PlayersList` snapshots of one server, because player indexes
+/// are unreliable (often always 0).
+///
+/// A player continues a session if the name is the same and the duration has grown
+/// by the time passed between snapshots. Score breaks ties between namesakes.
+#[derive(Debug)]
+pub struct SessionTracker {
+    sessions: Vec<Session>,
+    last_update: Option<SystemTime>,
+    next_id: u64,
+    tolerance: Duration,
+}
+
+impl Default for SessionTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SessionTracker {
+    pub fn new() -> Self {
+        Self {
+            sessions: vec![],
+            last_update: None,
+            next_id: 1,
+            tolerance: Duration::from_secs(10),
+        }
+    }
+
+    /// Allowed difference between the expected and the reported duration of a player.
+    pub fn set_tolerance(&mut self, tolerance: Duration) {
+        self.tolerance = tolerance;
+    }
+
+    /// Sessions of players present in the latest snapshot.
+    pub fn active(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    pub fn update(&mut self, list: &PlayersList) -> Vec<SessionEvent> {
+        self.update_at(list, SystemTime::now())
+    }
+
+    /// Same as `update` with the time when the snapshot was taken.
+    pub fn update_at(&mut self, list: &PlayersList, now: SystemTime) -> Vec<SessionEvent> {
+        let elapsed = self
+            .last_update
+            .and_then(|last| now.duration_since(last).ok())
+            .unwrap_or_default();
+        self.last_update = Some(now);
+
+        let players: Vec<_> = list
+            .players
+            .iter()
+            .map(|player| {
+                (
+                    player.name.to_string_lossy().into_owned(),
+                    player.score,
+                    player.duration,
+                )
+            })
+            .collect();
+
+        // Every admissible pair of (player, session) with its cost, the best pairs go first
+        let mut pairs = vec![];
+        for (player_index, (name, score, duration)) in players.iter().enumerate() {
+            for (session_index, session) in self.sessions.iter().enumerate() {
+                if &session.name != name || *duration < session.duration {
+                    continue;
+                }
+                let expected = session.duration.saturating_add(elapsed);
+                let deviation = duration.abs_diff(expected);
+                if deviation <= self.tolerance {
+                    let score_gap = (i64::from(*score) - i64::from(session.score)).unsigned_abs();
+                    pairs.push(((deviation, score_gap), player_index, session_index));
+                }
+            }
+        }
+        pairs.sort_by_key(|&(cost, ..)| cost);
+
+        let mut matched_players = vec![None; players.len()];
+        let mut matched_sessions = vec![false; self.sessions.len()];
+        for (_, player_index, session_index) in pairs {
+            if matched_players[player_index].is_none() && !matched_sessions[session_index] {
+                matched_players[player_index] = Some(session_index);
+                matched_sessions[session_index] = true;
+            }
+        }
+
+        let mut events = vec![];
+        let mut sessions = Vec::with_capacity(players.len());
+        for ((name, score, duration), matched) in players.into_iter().zip(matched_players) {
+            match matched {
+                Some(session_index) => {
+                    let mut session = self.sessions[session_index].clone();
+                    session.score = score;
+                    session.duration = duration;
+                    session.last_seen = now;
+                    sessions.push(session);
+                }
+                None => {
+                    let session = Session {
+                        id: self.next_id,
+                        name,
+                        score,
+                        joined: now.checked_sub(duration).unwrap_or(now),
+                        last_seen: now,
+                        left: None,
+                        duration,
+                    };
+                    self.next_id += 1;
+                    events.push(SessionEvent::Joined(session.clone()));
+                    sessions.push(session);
+                }
+            }
+        }
+        for (session, matched) in self.sessions.drain(..).zip(matched_sessions) {
+            if !matched {
+                events.push(SessionEvent::Left(Session {
+                    left: Some(now),
+                    ..session
+                }));
+            }
+        }
+        self.sessions = sessions;
+        events
+    }
+}
@@ -0,0 +1,264 @@
+use super::{InfoNew, PacketParser, QueryResult, RuleChange, Rules, ValveQuery};
+use crate::{ErrorKind, RateLimiter};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    net::SocketAddr,
+    ops::Deref,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    CameOnline,
+    WentOffline(ErrorKind),
+    /// First poll of the server failed, so it wasn't online since the start of watching.
+    Unreachable(ErrorKind),
+    MapChanged {
+        old: String,
+        new: String,
+    },
+    Filled,
+    Emptied,
+    PlayerJoined(String),
+    PlayerLeft(String),
+    RuleChanged(RuleChange),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub address: SocketAddr,
+    pub kind: EventKind,
+}
+
+struct Snapshot {
+    info: InfoNew,
+    players: Option<Vec<String>>,
+    rules: Option<Rules>,
+}
+
+/// Polls servers and reports differences between their successive snapshots.
+pub struct Watcher<P: PacketParser> {
+    servers: Vec<SocketAddr>,
+    interval: Duration,
+    timeout: Duration,
+    watch_players: bool,
+    watch_rules: bool,
+    limiter: Option<Arc<RateLimiter>>,
+    // `None` if server is offline, missing if it isn't polled yet
+    states: HashMap<SocketAddr, Option<Snapshot>>,
+    _parser: PhantomData<fn() -> P>,
+}
+
+impl<P: PacketParser> Watcher<P> {
+    pub fn new(servers: Vec<SocketAddr>, interval: Duration) -> Self {
+        Self {
+            servers,
+            interval,
+            timeout: Duration::from_secs(5),
+            watch_players: true,
+            watch_rules: true,
+            limiter: None,
+            states: HashMap::new(),
+            _parser: PhantomData,
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Whether to query A2S_PLAYER to report joined and left players.
+    pub fn set_watch_players(&mut self, watch: bool) {
+        self.watch_players = watch;
+    }
+
+    /// Whether to query A2S_RULES to report changed rules.
+    pub fn set_watch_rules(&mut self, watch: bool) {
+        self.watch_rules = watch;
+    }
+
+    pub fn set_rate_limiter(&mut self, limiter: Option<Arc<RateLimiter>>) {
+        self.limiter = limiter;
+    }
+
+    /// Queries every server once and returns events since the previous poll.
+    pub fn poll(&mut self) -> Vec<Event> {
+        let mut events = vec![];
+        for &address in &self.servers {
+            let snapshot = self.snapshot(address);
+            let previous = self.states.remove(&address);
+            let mut push = |kind| events.push(Event { address, kind });
+            let snapshot = match (previous, snapshot) {
+                // Offline servers are reported only once
+                (Some(None), Err(_)) => None,
+                (None, Err(err)) => {
+                    push(EventKind::Unreachable(err.kind()));
+                    None
+                }
+                (Some(Some(_)), Err(err)) => {
+                    push(EventKind::WentOffline(err.kind()));
+                    None
+                }
+                (None, Ok(snapshot)) | (Some(None), Ok(snapshot)) => {
+                    push(EventKind::CameOnline);
+                    Some(snapshot)
+                }
+                (Some(Some(old)), Ok(mut new)) => {
+                    compare(&old, &mut new, &mut push);
+                    Some(new)
+                }
+            };
+            self.states.insert(address, snapshot);
+        }
+        events
+    }
+
+    /// Polls servers every interval and passes events to `callback` while it returns `true`.
+    pub fn run<F: FnMut(Event) -> bool>(self, callback: F) {
+        self.run_with(
+            |pause| {
+                thread::sleep(pause);
+                true
+            },
+            callback,
+        )
+    }
+
+    /// Same as `run`, but pauses between polls with `wait`, which returns `false` to stop.
+    fn run_with<W, F>(mut self, mut wait: W, mut callback: F)
+    where
+        W: FnMut(Duration) -> bool,
+        F: FnMut(Event) -> bool,
+    {
+        loop {
+            let start = Instant::now();
+            for event in self.poll() {
+                if !callback(event) {
+                    return;
+                }
+            }
+            if !wait(self.interval.saturating_sub(start.elapsed())) {
+                return;
+            }
+        }
+    }
+
+    fn snapshot(&self, address: SocketAddr) -> QueryResult<Snapshot> {
+        let mut query =
+            ValveQuery::<P>::connected(address, self.timeout).map_err(super::PacketError::from)?;
+        query.set_rate_limiter(self.limiter.clone());
+        let info = query.a2s_info_new()?;
+        let players = if self.watch_players {
+            query
+                .a2s_player_challenge()
+                .and_then(|challenge| query.a2s_players_with_app_id(challenge, info.steamid))
+                .map(|list| {
+                    list.players
+                        .iter()
+                        .map(|player| player.name.to_string_lossy().into_owned())
+                        .collect()
+                })
+                .ok()
+        } else {
+            None
+        };
+        let rules = if self.watch_rules {
+            query
+                .a2s_rules_challenge()
+                .and_then(|challenge| query.a2s_rules(challenge))
+                .map(Rules::from)
+                .ok()
+        } else {
+            None
+        };
+        Ok(Snapshot {
+            info,
+            players,
+            rules,
+        })
+    }
+}
+
+impl<P: PacketParser + 'static> Watcher<P> {
+    /// Polls servers in a background thread, which stops once the handle is dropped.
+    pub fn spawn(self) -> WatcherHandle {
+        let (sender, events) = mpsc::channel();
+        let (stop, stopped) = mpsc::channel();
+        thread::spawn(move || {
+            self.run_with(
+                // Dropped handle disconnects the channel and wakes the thread up
+                |pause| stopped.recv_timeout(pause) == Err(RecvTimeoutError::Timeout),
+                |event| sender.send(event).is_ok(),
+            )
+        });
+        WatcherHandle {
+            events,
+            _stop: stop,
+        }
+    }
+}
+
+/// Receiver of events of a spawned `Watcher`.
+pub struct WatcherHandle {
+    events: Receiver<Event>,
+    // Never sent to, only disconnected on drop
+    _stop: Sender<()>,
+}
+
+impl Deref for WatcherHandle {
+    type Target = Receiver<Event>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.events
+    }
+}
+
+/// Compares snapshots, keeping previous players and rules if they weren't queried this time.
+fn compare(old: &Snapshot, new: &mut Snapshot, push: &mut impl FnMut(EventKind)) {
+    if old.info.map != new.info.map {
+        push(EventKind::MapChanged {
+            old: old.info.map.to_string_lossy().into_owned(),
+            new: new.info.map.to_string_lossy().into_owned(),
+        });
+    }
+    let (old_players, new_players) = (old.info.players, new.info.players);
+    if old_players < old.info.max_players && new_players >= new.info.max_players {
+        push(EventKind::Filled);
+    }
+    if old_players > 0 && new_players == 0 {
+        push(EventKind::Emptied);
+    }
+
+    match (&old.players, &new.players) {
+        (Some(old), Some(new)) => {
+            let mut left = old.clone();
+            for name in new {
+                match left.iter().position(|old_name| old_name == name) {
+                    Some(index) => {
+                        left.swap_remove(index);
+                    }
+                    None => push(EventKind::PlayerJoined(name.clone())),
+                }
+            }
+            left.into_iter()
+                .for_each(|name| push(EventKind::PlayerLeft(name)));
+        }
+        (Some(old), None) => new.players = Some(old.clone()),
+        _ => {}
+    }
+
+    match (&old.rules, &new.rules) {
+        (Some(old), Some(new)) => old
+            .diff(new)
+            .into_iter()
+            .for_each(|change| push(EventKind::RuleChanged(change))),
+        (Some(old), None) => new.rules = Some(old.clone()),
+        _ => {}
+    }
+}
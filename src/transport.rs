@@ -0,0 +1,292 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Error as IOError, ErrorKind, Result as IOResult, Write},
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    path::Path,
+    sync::Mutex,
+    time::Instant,
+};
+
+/// Connected datagram transport, which queries are sent over.
+pub trait Transport {
+    fn send(&self, buf: &[u8]) -> IOResult<usize>;
+
+    fn recv(&self, buf: &mut [u8]) -> IOResult<usize>;
+
+    fn peer_addr(&self) -> IOResult<SocketAddr>;
+
+    /// Switches to another peer, which isn't supported by default.
+    fn connect(&self, addr: SocketAddr) -> IOResult<()> {
+        let _ = addr;
+        Err(IOError::new(
+            ErrorKind::Unsupported,
+            "Transport can't switch peers",
+        ))
+    }
+}
+
+impl Transport for UdpSocket {
+    fn send(&self, buf: &[u8]) -> IOResult<usize> {
+        UdpSocket::send(self, buf)
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> IOResult<usize> {
+        UdpSocket::recv(self, buf)
+    }
+
+    fn peer_addr(&self) -> IOResult<SocketAddr> {
+        UdpSocket::peer_addr(self)
+    }
+
+    fn connect(&self, addr: SocketAddr) -> IOResult<()> {
+        UdpSocket::connect(self, addr)
+    }
+}
+
+/// Resolved addresses of a peer, which are tried in turn while requests time out.
+#[derive(Debug, Default)]
+pub(crate) struct Failover {
+    addresses: Vec<SocketAddr>,
+    current: usize,
+}
+
+impl Failover {
+    /// Connects `socket` to the first of `addrs` of the same family as the socket.
+    pub fn connect<A: ToSocketAddrs>(socket: &UdpSocket, addrs: A) -> IOResult<Self> {
+        let ipv6 = socket.local_addr()?.is_ipv6();
+        let addresses: Vec<_> = addrs
+            .to_socket_addrs()?
+            .filter(|addr| addr.is_ipv6() == ipv6)
+            .collect();
+        let first = addresses.first().ok_or_else(|| {
+            IOError::new(
+                ErrorKind::InvalidInput,
+                "No addresses of the socket's family to connect to",
+            )
+        })?;
+        socket.connect(first)?;
+        Ok(Self {
+            addresses,
+            current: 0,
+        })
+    }
+
+    /// Number of attempts to make a request before giving up, one per address.
+    pub fn attempts(&self) -> usize {
+        self.addresses.len().max(1)
+    }
+
+    /// Index of the connected address.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Connects to the next address after a timeout of `from`, unless a concurrent request
+    /// has moved on from it already.
+    pub fn advance<T: Transport>(&mut self, transport: &T, from: usize) -> IOResult<()> {
+        if self.addresses.len() > 1 && self.current == from {
+            self.current = (self.current + 1) % self.addresses.len();
+            transport.connect(self.addresses[self.current])?;
+        }
+        Ok(())
+    }
+}
+
+fn is_timeout(err: &IOError) -> bool {
+    matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut)
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [_, _] => u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Transport, which logs datagrams passed through another one.
+///
+/// Every line of the log is seconds since the start of recording, direction and datagram
+/// in hex, e.g. `0.001532 < ffffffff41...`. `>` is sent, `<` is received and `!` is
+/// a timeout of receiving. The log starts with the peer's address, e.g. `peer 1.2.3.4:27015`.
+pub struct Recording<T: Transport, W: Write> {
+    inner: T,
+    log: Mutex<(W, bool)>,
+    start: Instant,
+}
+
+impl<T: Transport> Recording<T, BufWriter<File>> {
+    pub fn create<A: AsRef<Path>>(inner: T, path: A) -> IOResult<Self> {
+        Ok(Self::new(inner, BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<T: Transport, W: Write> Recording<T, W> {
+    pub fn new(inner: T, log: W) -> Self {
+        Self {
+            inner,
+            log: Mutex::new((log, false)),
+            start: Instant::now(),
+        }
+    }
+
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    fn write(&self, direction: char, data: &[u8]) -> IOResult<()> {
+        let mut log = self.log.lock().unwrap();
+        let (log, peer_written) = &mut *log;
+        if !*peer_written {
+            writeln!(log, "peer {}", self.inner.peer_addr()?)?;
+            *peer_written = true;
+        }
+        writeln!(
+            log,
+            "{:.6} {} {}",
+            self.start.elapsed().as_secs_f64(),
+            direction,
+            to_hex(data)
+        )?;
+        log.flush()
+    }
+}
+
+impl<T: Transport, W: Write> Transport for Recording<T, W> {
+    fn send(&self, buf: &[u8]) -> IOResult<usize> {
+        let size = self.inner.send(buf)?;
+        self.write('>', &buf[..size])?;
+        Ok(size)
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> IOResult<usize> {
+        match self.inner.recv(buf) {
+            Ok(size) => {
+                self.write('<', &buf[..size])?;
+                Ok(size)
+            }
+            Err(err) => {
+                if is_timeout(&err) {
+                    self.write('!', &[])?;
+                }
+                Err(err)
+            }
+        }
+    }
+
+    fn peer_addr(&self) -> IOResult<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    fn connect(&self, addr: SocketAddr) -> IOResult<()> {
+        self.inner.connect(addr)?;
+        // The new peer is logged before the next datagram
+        self.log.lock().unwrap().1 = false;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+enum Entry {
+    Sent(Vec<u8>),
+    Received(Vec<u8>),
+    Timeout,
+}
+
+/// Transport, which plays back a log written by `Recording` without any network.
+///
+/// Sent datagrams must be the same as recorded ones, received datagrams are served
+/// in the recorded order.
+#[derive(Debug)]
+pub struct Replay {
+    peer: Option<SocketAddr>,
+    entries: Mutex<VecDeque<Entry>>,
+}
+
+fn invalid_data(message: String) -> IOError {
+    IOError::new(ErrorKind::InvalidData, message)
+}
+
+impl Replay {
+    pub fn open<A: AsRef<Path>>(path: A) -> IOResult<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn from_reader<R: BufRead>(log: R) -> IOResult<Self> {
+        let mut peer = None;
+        let mut entries = VecDeque::new();
+        for (number, line) in log.lines().enumerate() {
+            let line = line?;
+            let invalid = || invalid_data(format!("Invalid line {} of the log", number + 1));
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next(), parts.next().unwrap_or("")) {
+                (None, ..) => continue,
+                (Some("peer"), Some(address), _) => {
+                    peer = Some(address.parse().map_err(|_| invalid())?)
+                }
+                (Some(_), Some(">"), hex) => {
+                    entries.push_back(Entry::Sent(from_hex(hex).ok_or_else(invalid)?))
+                }
+                (Some(_), Some("<"), hex) => {
+                    entries.push_back(Entry::Received(from_hex(hex).ok_or_else(invalid)?))
+                }
+                (Some(_), Some("!"), _) => entries.push_back(Entry::Timeout),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(Self {
+            peer,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Whether every recorded datagram is played back.
+    pub fn is_finished(&self) -> bool {
+        self.entries.lock().unwrap().is_empty()
+    }
+}
+
+impl Transport for Replay {
+    fn send(&self, buf: &[u8]) -> IOResult<usize> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.pop_front() {
+            Some(Entry::Sent(data)) if data == buf => Ok(buf.len()),
+            entry => Err(invalid_data(format!(
+                "Sent {}, but recorded {:?}",
+                to_hex(buf),
+                entry
+            ))),
+        }
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> IOResult<usize> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.pop_front() {
+            Some(Entry::Received(data)) => {
+                // Datagrams are truncated to the buffer like by sockets
+                let size = data.len().min(buf.len());
+                buf[..size].copy_from_slice(&data[..size]);
+                Ok(size)
+            }
+            Some(Entry::Timeout) => Err(IOError::new(ErrorKind::TimedOut, "Recorded timeout")),
+            Some(entry) => {
+                let err = invalid_data(format!("Receiving, but recorded {:?}", entry));
+                entries.push_front(entry);
+                Err(err)
+            }
+            None => Err(IOError::new(ErrorKind::UnexpectedEof, "Recording is over")),
+        }
+    }
+
+    fn peer_addr(&self) -> IOResult<SocketAddr> {
+        self.peer
+            .ok_or_else(|| IOError::new(ErrorKind::NotConnected, "Peer isn't recorded"))
+    }
+}
@@ -0,0 +1,278 @@
+use vquery::server::*;
+
+fn the_ship_info() -> Vec<u8> {
+    let mut data = vec![0x07];
+    data.extend(b"Ship Server\0shipmap\0ship\0The Ship\0");
+    data.extend(&THE_SHIP_APP_ID.to_le_bytes());
+    data.extend(&[4, 16, 0, b'd', b'w', 0, 1]);
+    data.extend(&[2, 3, 240]); // mode, witnesses, duration
+    data.extend(b"1.0.0.4\0");
+    data.push(0x00); // edf
+    data
+}
+
+#[test]
+fn the_ship_info_extension() {
+    let data = the_ship_info();
+    let (rest, info) = InfoNew::parse(&data).unwrap();
+    assert!(rest.is_empty());
+    let the_ship = info.the_ship.unwrap();
+    assert_eq!(the_ship.mode, 2);
+    assert_eq!(the_ship.witnesses, 3);
+    assert_eq!(the_ship.duration, 240);
+    assert_eq!(info.version.to_str().unwrap(), "1.0.0.4");
+}
+
+#[test]
+fn the_ship_players_extension() {
+    let mut data = vec![2];
+    data.extend(b"\0first\0");
+    data.extend(&10_i32.to_le_bytes());
+    data.extend(&1.5_f32.to_le_bytes());
+    data.extend(b"\0second\0");
+    data.extend(&20_i32.to_le_bytes());
+    data.extend(&2.5_f32.to_le_bytes());
+    data.extend(&1_i32.to_le_bytes());
+    data.extend(&500_i32.to_le_bytes());
+    data.extend(&2_i32.to_le_bytes());
+    data.extend(&1000_i32.to_le_bytes());
+
+    let (rest, list) = PlayersList::parse(&data, THE_SHIP_APP_ID).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(list.players.len(), 2);
+    let the_ship = list.players[1].the_ship.as_ref().unwrap();
+    assert_eq!(the_ship.deaths, 2);
+    assert_eq!(the_ship.money, 1000);
+
+    let (_, list) = PlayersList::parse(&data[..data.len() - 16], 240).unwrap();
+    assert_eq!(list.players.len(), 2);
+    assert!(list.players.iter().all(|player| player.the_ship.is_none()));
+}
+
+fn rules_list(pairs: &[(&str, &str)]) -> RulesList {
+    let mut data = (pairs.len() as u16).to_le_bytes().to_vec();
+    for (key, value) in pairs {
+        data.extend(key.as_bytes());
+        data.push(0);
+        data.extend(value.as_bytes());
+        data.push(0);
+    }
+    RulesList::parse(&data).unwrap().1
+}
+
+#[test]
+fn typed_rules() {
+    let rules = Rules::from(rules_list(&[
+        ("sv_gravity", "800"),
+        ("mp_timelimit", "30"),
+        ("sv_password", "0"),
+        ("mp_friendlyfire", "1"),
+        ("tf_gamemode_ctf", "1"),
+        ("tf_gamemode_cp", "0"),
+        ("tf_gamemode_payload", "1"),
+    ]));
+    assert_eq!(rules.len(), 7);
+    assert_eq!(rules.get_i64("sv_gravity"), Some(800));
+    assert_eq!(rules.gravity(), Some(800.0));
+    assert_eq!(
+        rules.time_limit(),
+        Some(std::time::Duration::from_secs(1800))
+    );
+    assert_eq!(rules.has_password(), Some(false));
+    assert_eq!(rules.friendly_fire(), Some(true));
+    assert_eq!(rules.tf_game_modes(), vec!["ctf", "payload"]);
+    assert_eq!(rules.get_bool("sv_cheats"), None);
+
+    let rules = Rules::from(rules_list(&[("mp_timelimit", "1e38")]));
+    assert_eq!(rules.time_limit(), Some(std::time::Duration::MAX));
+}
+
+#[test]
+fn rules_diff() {
+    let old = Rules::from(rules_list(&[("a", "1"), ("b", "2"), ("c", "3")]));
+    let new = Rules::from(rules_list(&[("b", "2"), ("c", "4"), ("d", "5")]));
+    assert_eq!(
+        old.diff(&new),
+        vec![
+            RuleChange::Removed {
+                key: "a".into(),
+                value: "1".into()
+            },
+            RuleChange::Changed {
+                key: "c".into(),
+                old: "3".into(),
+                new: "4".into()
+            },
+            RuleChange::Added {
+                key: "d".into(),
+                value: "5".into()
+            },
+        ]
+    );
+    assert!(new.diff(&new).is_empty());
+}
+
+fn info_head() -> Vec<u8> {
+    let mut data = vec![0x11];
+    data.extend(b"name\0map\0folder\0game\0");
+    data.extend(&[0xF0, 0x00, 1, 2, 0, b'd', b'l', 0, 1]);
+    data.extend(b"1.0\0");
+    data
+}
+
+#[test]
+fn lenient_missing_extra_data() {
+    let data = info_head();
+    assert!(InfoNew::parse(&data).is_err());
+    let (_, info) = InfoNew::parse_lenient(&data).unwrap();
+    assert_eq!(info.warnings, vec![Warning::MissingExtraData]);
+    assert_eq!(info.value.extra_data.edf, 0);
+    assert_eq!(info.value.version.to_str().unwrap(), "1.0");
+}
+
+#[test]
+fn lenient_truncated_extra_data() {
+    let mut data = info_head();
+    data.push(0x80 | 0x20 | 0x01);
+    data.extend(&27015_i16.to_le_bytes());
+    data.extend(b"keywords");
+    let (_, info) = InfoNew::parse_lenient(&data).unwrap();
+    assert_eq!(
+        info.warnings,
+        vec![
+            Warning::TruncatedExtraData { announced: 0xA1 },
+            Warning::TrailingBytes(8)
+        ]
+    );
+    let extra_data = info.value.extra_data;
+    assert_eq!(extra_data.edf, 0x80);
+    assert_eq!(extra_data.port, Some(27015));
+    assert!(extra_data.keywords.is_none());
+    assert!(extra_data.gameid.is_none());
+}
+
+#[test]
+fn lenient_half_of_source_tv() {
+    let mut data = info_head();
+    data.push(0x80 | 0x40 | 0x01 | 0x02);
+    data.extend(&27015_i16.to_le_bytes());
+    data.extend(&27020_i16.to_le_bytes());
+    data.extend(b"tv");
+    let (_, info) = InfoNew::parse_lenient(&data).unwrap();
+    assert_eq!(
+        info.warnings,
+        vec![
+            Warning::TruncatedExtraData { announced: 0xC3 },
+            Warning::TrailingBytes(4)
+        ]
+    );
+    let extra_data = info.value.extra_data;
+    // Unknown flags are kept
+    assert_eq!(extra_data.edf, 0x82);
+    assert_eq!(extra_data.port, Some(27015));
+    assert!(extra_data.port_source_tv.is_none());
+    assert!(extra_data.name_source_tv.is_none());
+
+    let mut reply = b"I".to_vec();
+    reply.extend(&data);
+    match decode_info_new(&reply).unwrap_err() {
+        Error::A2SParse(parse) => {
+            assert_eq!(parse.field, "extra_data.name_source_tv");
+            assert_eq!(parse.kind, None);
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn lenient_short_players_list() {
+    let mut data = vec![3];
+    data.extend(b"\0first\0");
+    data.extend(&10_i32.to_le_bytes());
+    data.extend(&1.5_f32.to_le_bytes());
+    data.extend(b"\0sec");
+    let (_, list) = PlayersList::parse_lenient(&data, 0).unwrap();
+    assert_eq!(list.value.players.len(), 1);
+    assert_eq!(
+        list.warnings,
+        vec![
+            Warning::PlayersCount {
+                announced: 3,
+                parsed: 1
+            },
+            Warning::TrailingBytes(4)
+        ]
+    );
+
+    let (_, list) = PlayersList::parse_lenient(&data, THE_SHIP_APP_ID).unwrap();
+    assert_eq!(list.value.players.len(), 1);
+    assert_eq!(list.warnings[0], Warning::MissingTheShipData);
+}
+
+#[test]
+fn borrowed_replies() {
+    let mut info = b"I".to_vec();
+    info.extend(the_ship_info());
+    info.pop();
+    info.push(0x60); // edf with SourceTV and keywords
+    info.extend(&27020_i16.to_le_bytes());
+    info.extend(b"tv\0ship,secure\0");
+    let borrowed = decode_info_new_ref(&info).unwrap();
+    assert_eq!(borrowed.map.to_str().unwrap(), "shipmap");
+    assert_eq!(
+        borrowed.extra_data.keywords.unwrap().to_str().unwrap(),
+        "ship,secure"
+    );
+    assert_eq!(
+        format!("{:?}", borrowed.to_owned()),
+        format!("{:?}", decode_info_new(&info).unwrap())
+    );
+
+    let mut players = b"D\x01\x00first\x00".to_vec();
+    players.extend(&10_i32.to_le_bytes());
+    players.extend(&1.5_f32.to_le_bytes());
+    players.extend(&1_i32.to_le_bytes());
+    players.extend(&500_i32.to_le_bytes());
+    let borrowed = decode_players_ref(&players, THE_SHIP_APP_ID).unwrap();
+    assert_eq!(borrowed.players[0].name.to_str().unwrap(), "first");
+    assert_eq!(
+        format!("{:?}", borrowed.to_owned()),
+        format!("{:?}", decode_players(&players, THE_SHIP_APP_ID).unwrap())
+    );
+
+    let rules = b"E\x01\x00sv_gravity\x00800\x00";
+    let borrowed = decode_rules_ref(rules).unwrap();
+    assert_eq!(borrowed.rules[0].value.to_str().unwrap(), "800");
+    assert_eq!(
+        format!("{:?}", borrowed.to_owned()),
+        format!("{:?}", decode_rules(rules).unwrap())
+    );
+
+    assert!(matches!(
+        decode_info_new_ref(b"I\x11name"),
+        Err(Error::A2SParse(_))
+    ));
+}
+
+#[test]
+fn unexpected_header() {
+    // Obsolete goldsrc reply to A2S_INFO
+    assert!(matches!(
+        decode_info_new(b"m127.0.0.1:27015\0"),
+        Err(Error::UnexpectedHeader {
+            expected: b'I',
+            found: Some(b'm')
+        })
+    ));
+    assert!(matches!(
+        decode_rules(b""),
+        Err(Error::UnexpectedHeader {
+            expected: b'E',
+            found: None
+        })
+    ));
+    assert!(matches!(
+        decode_players(b"I\x11", 0),
+        Err(Error::UnexpectedHeader { .. })
+    ));
+}
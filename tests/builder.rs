@@ -0,0 +1,77 @@
+mod common;
+
+use common::{info_reply, mock_server};
+use std::time::Duration;
+use vquery::{
+    server::{PacketError, SourceParser},
+    ErrorKind, Limits, QueryBuilder,
+};
+
+#[test]
+fn socket_options() {
+    let builder = QueryBuilder::new()
+        .local_addr("127.0.0.1:0".parse().unwrap())
+        .read_timeout(Some(Duration::from_secs(2)))
+        .write_timeout(Some(Duration::from_secs(3)))
+        .recv_buffer_size(64 * 1024)
+        .ttl(42)
+        .dscp(46)
+        .reuse_address(true);
+    let socket = builder.socket().unwrap();
+    assert!(socket.local_addr().unwrap().ip().is_loopback());
+    assert_eq!(socket.read_timeout().unwrap(), Some(Duration::from_secs(2)));
+    assert_eq!(
+        socket.write_timeout().unwrap(),
+        Some(Duration::from_secs(3))
+    );
+    assert_eq!(socket.ttl().unwrap(), 42);
+
+    // Both sockets set SO_REUSEADDR, so they can share the port
+    let shared = builder
+        .clone()
+        .local_addr(socket.local_addr().unwrap())
+        .socket();
+    assert!(shared.is_ok());
+
+    let query = builder.servers_query().unwrap();
+    assert_eq!(query.timeout().unwrap(), Some(Duration::from_secs(2)));
+}
+
+#[test]
+fn packet_size() {
+    let long_name = "x".repeat(1500);
+    let address = mock_server(move |_| vec![info_reply(&long_name, 1)]);
+    let builder = QueryBuilder::new()
+        .local_addr("127.0.0.1:0".parse().unwrap())
+        .read_timeout(Some(Duration::from_secs(1)));
+
+    // Reply is truncated to the default packet size
+    let query = builder.valve_query::<SourceParser>().unwrap();
+    query.connect(address).unwrap();
+    let err = query.a2s_info_new().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MalformedReply);
+
+    let query = builder
+        .clone()
+        .packet_size(2048)
+        .valve_query::<SourceParser>()
+        .unwrap();
+    query.connect(address).unwrap();
+    assert_eq!(query.a2s_info_new().unwrap().map.to_bytes().len(), 1500);
+    assert_eq!(query.packet_size(), 2048);
+
+    // Packet size doesn't lift the limit of fragment size
+    let query = builder
+        .packet_size(2048)
+        .limits(Limits {
+            fragment_size: 1024,
+            ..Limits::default()
+        })
+        .valve_query::<SourceParser>()
+        .unwrap();
+    query.connect(address).unwrap();
+    assert!(matches!(
+        query.a2s_info_new(),
+        Err(vquery::server::Error::Packet(PacketError::Limit(_)))
+    ));
+}
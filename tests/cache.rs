@@ -0,0 +1,107 @@
+mod common;
+
+use common::{info_reply, mock_server};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Barrier,
+    },
+    thread,
+    time::Duration,
+};
+use vquery::server::{Cache, ServerCache, SourceParser};
+
+#[test]
+fn concurrent_requests_are_coalesced() {
+    let cache = Arc::new(Cache::<u8, u8>::new(Duration::from_secs(60)));
+    let fetches = Arc::new(AtomicUsize::new(0));
+    let barrier = Arc::new(Barrier::new(8));
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let (cache, fetches, barrier) = (cache.clone(), fetches.clone(), barrier.clone());
+            thread::spawn(move || {
+                barrier.wait();
+                *cache
+                    .get(&1, || -> Result<u8, ()> {
+                        fetches.fetch_add(1, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(100));
+                        Ok(42)
+                    })
+                    .unwrap()
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 42);
+    }
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn stale_value_during_refresh() {
+    let cache = Arc::new(Cache::<u8, u8>::new(Duration::from_millis(50)));
+    cache.get(&1, || Ok::<_, ()>(1)).unwrap();
+    thread::sleep(Duration::from_millis(60));
+
+    let refresher = {
+        let cache = cache.clone();
+        thread::spawn(move || {
+            cache.get(&1, || {
+                thread::sleep(Duration::from_millis(200));
+                Ok::<_, ()>(2)
+            })
+        })
+    };
+    thread::sleep(Duration::from_millis(50));
+    // Refresh is running, so the stale value is served without fetching
+    assert_eq!(*cache.get(&1, || Err(())).unwrap(), 1);
+    assert_eq!(*refresher.join().unwrap().unwrap(), 2);
+    assert_eq!(*cache.get(&1, || Err(())).unwrap(), 2);
+
+    // Failures aren't cached
+    cache.invalidate(&1);
+    assert!(cache.get(&1, || Err(())).is_err());
+    assert_eq!(*cache.get(&1, || Ok::<_, ()>(3)).unwrap(), 3);
+}
+
+#[test]
+fn panicking_fetch() {
+    let cache = Arc::new(Cache::<u8, u8>::new(Duration::from_secs(60)));
+    let panicking = {
+        let cache = cache.clone();
+        thread::spawn(move || {
+            cache.get(&1, || -> Result<u8, ()> {
+                thread::sleep(Duration::from_millis(100));
+                panic!("fetch failed")
+            })
+        })
+    };
+    thread::sleep(Duration::from_millis(50));
+
+    // Waits for the panicking fetch, then fetches the value itself
+    let (sender, receiver) = mpsc::channel();
+    let waiter = cache.clone();
+    thread::spawn(move || sender.send(*waiter.get(&1, || Ok::<_, ()>(7)).unwrap()));
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(7));
+    assert!(panicking.join().is_err());
+}
+
+#[test]
+fn server_info_is_cached() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let counter = requests.clone();
+    let address = mock_server(move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        vec![info_reply("de_dust2", 3)]
+    });
+
+    let cache = ServerCache::<SourceParser>::new(Duration::from_secs(60), Duration::from_secs(1));
+    for _ in 0..3 {
+        assert_eq!(cache.info(address).unwrap().players, 3);
+    }
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    cache.invalidate(address);
+    cache.info(address).unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,59 @@
+#![allow(dead_code)]
+
+use std::{
+    net::{SocketAddr, UdpSocket},
+    thread,
+};
+
+/// Reply of a Source server with app id 730 to A2S_INFO.
+pub fn info_reply(map: &str, players: u8) -> Vec<u8> {
+    let mut reply = b"\xFF\xFF\xFF\xFFI\x11Test server\0".to_vec();
+    reply.extend(map.as_bytes());
+    reply.extend(b"\0csgo\0Counter-Strike\0");
+    reply.extend(&730_i16.to_le_bytes());
+    reply.extend(&[players, 16, 1, b'd', b'l', 0, 1]);
+    reply.extend(b"1.0\0\x00");
+    reply
+}
+
+/// Spawns an UDP server which answers every request with datagrams made by `respond`.
+pub fn mock_server<F>(respond: F) -> SocketAddr
+where
+    F: Fn(&[u8]) -> Vec<Vec<u8>> + Send + 'static,
+{
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = socket.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut buf = [0; 1400];
+        while let Ok((size, client)) = socket.recv_from(&mut buf) {
+            for reply in respond(&buf[..size]) {
+                socket.send_to(&reply, client).unwrap();
+            }
+        }
+    });
+    address
+}
+
+/// Address where nobody listens.
+pub fn dead_address() -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.local_addr().unwrap()
+}
+
+/// Reply of a master server with `servers`, which ends the list if `last` is set.
+pub fn master_reply(servers: &[&str], last: bool) -> Vec<u8> {
+    let mut reply = b"\xFF\xFF\xFF\xFF\x66\x0A".to_vec();
+    let terminator = last.then_some("0.0.0.0:0");
+    for server in servers.iter().copied().chain(terminator) {
+        let server: std::net::SocketAddrV4 = server.parse().unwrap();
+        reply.extend(&server.ip().octets());
+        reply.extend(&server.port().to_be_bytes());
+    }
+    reply
+}
+
+/// Seed of a master request, i.e. the address after the region byte.
+pub fn master_seed(request: &[u8]) -> &str {
+    let end = request[2..].iter().position(|&b| b == 0).unwrap();
+    std::str::from_utf8(&request[2..2 + end]).unwrap()
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use common::{dead_address, master_reply, master_seed, mock_server};
+use std::{net::SocketAddr, time::Duration};
+use vquery::{
+    master::{Error, NotTerminated, Region, ServersQuery},
+    ErrorKind,
+};
+
+fn query(master: SocketAddr) -> ServersQuery {
+    let query = ServersQuery::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    query.set_timeout(Some(Duration::from_secs(1))).unwrap();
+    query.connect(master).unwrap();
+    query
+}
+
+fn addresses(results: Vec<Result<std::net::SocketAddrV4, Error>>) -> Vec<String> {
+    results
+        .into_iter()
+        .map(|server| server.unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn pages_and_duplicates() {
+    let master = mock_server(|request| match master_seed(request) {
+        "0.0.0.0:0" => vec![master_reply(&["10.0.0.1:1", "10.0.0.2:1"], false)],
+        "10.0.0.2:1" => vec![master_reply(
+            &["10.0.0.2:1", "10.0.0.1:1", "10.0.0.3:1"],
+            false,
+        )],
+        _ => vec![master_reply(&["10.0.0.3:1", "10.0.0.4:1"], true)],
+    });
+    let query = query(master);
+
+    let mut servers = query.iter(Region::All, &[]);
+    let results: Vec<_> = servers.by_ref().collect();
+    assert_eq!(
+        addresses(results),
+        ["10.0.0.1:1", "10.0.0.2:1", "10.0.0.3:1", "10.0.0.4:1"]
+    );
+    assert_eq!(servers.pages(), 3);
+    assert!(servers.next().is_none());
+
+    // Only the seed at the start of a page is skipped without dedup
+    let mut servers = query.iter(Region::All, &[]);
+    servers.set_dedup(false);
+    assert_eq!(
+        addresses(servers.collect()),
+        [
+            "10.0.0.1:1",
+            "10.0.0.2:1",
+            "10.0.0.1:1",
+            "10.0.0.3:1",
+            "10.0.0.4:1"
+        ]
+    );
+}
+
+#[test]
+fn repeated_seed() {
+    // Second page points back to the first one
+    let master = mock_server(|request| match master_seed(request) {
+        "0.0.0.0:0" => vec![master_reply(&["10.0.0.1:1", "10.0.0.2:1"], false)],
+        _ => vec![master_reply(
+            &["10.0.0.2:1", "10.0.0.3:1", "10.0.0.2:1"],
+            false,
+        )],
+    });
+    let query = query(master);
+
+    let results: Vec<_> = query.iter(Region::All, &[]).collect();
+    assert_eq!(results.len(), 4);
+    let err = results.into_iter().last().unwrap().unwrap_err();
+    assert!(matches!(
+        err,
+        Error::NotTerminated(NotTerminated::RepeatedSeed(seed)) if seed.to_string() == "10.0.0.2:1"
+    ));
+    assert_eq!(err.kind(), ErrorKind::MalformedReply);
+}
+
+#[test]
+fn empty_page() {
+    let master = mock_server(|request| match master_seed(request) {
+        "0.0.0.0:0" => vec![master_reply(&["10.0.0.1:1", "10.0.0.2:1"], false)],
+        _ => vec![master_reply(&[], false)],
+    });
+    let query = query(master);
+
+    let mut servers = query.iter(Region::All, &[]);
+    let results: Vec<_> = servers.by_ref().collect();
+    assert_eq!(results.len(), 3);
+    let err = results.into_iter().last().unwrap().unwrap_err();
+    assert!(matches!(
+        err,
+        Error::NotTerminated(NotTerminated::EmptyPage)
+    ));
+    assert_eq!(err.kind(), ErrorKind::MalformedReply);
+    assert_eq!(servers.pages(), 2);
+    assert!(servers.next().is_none());
+}
+
+#[test]
+fn page_limit() {
+    // Every page leads to a new one
+    let master = mock_server(|request| {
+        let seed: std::net::SocketAddrV4 = master_seed(request).parse().unwrap();
+        let next = format!("10.0.0.1:{}", seed.port() + 1);
+        vec![master_reply(&[&next], false)]
+    });
+    let query = query(master);
+
+    let mut servers = query.iter(Region::All, &[]);
+    servers.set_max_pages(5);
+    let results: Vec<_> = servers.by_ref().collect();
+    assert_eq!(results.len(), 6);
+    let err = results.into_iter().last().unwrap().unwrap_err();
+    assert!(matches!(
+        err,
+        Error::NotTerminated(NotTerminated::PageLimit(5))
+    ));
+    assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+    assert_eq!(servers.pages(), 5);
+}
+
+#[test]
+fn failed_requests() {
+    let query = ServersQuery::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    query.set_timeout(Some(Duration::from_millis(50))).unwrap();
+    query.connect(dead_address()).unwrap();
+
+    let mut servers = query.iter(Region::All, &[]);
+    servers.set_max_failures(2);
+    let results: Vec<_> = servers.by_ref().collect();
+    assert_eq!(results.len(), 2);
+    assert!(results.into_iter().all(|result| result.is_err()));
+    assert!(servers.next().is_none());
+    assert_eq!(servers.pages(), 0);
+}
@@ -0,0 +1,117 @@
+use std::{
+    io::{Error as IOError, ErrorKind as IOErrorKind},
+    net::UdpSocket,
+    thread,
+    time::Duration,
+};
+use vquery::{server::*, Error, ErrorKind};
+
+#[test]
+fn io_errors_kind() {
+    let timeout = Error::from(PacketError::Io(IOError::from(IOErrorKind::WouldBlock)));
+    assert_eq!(timeout.kind(), ErrorKind::Timeout);
+    assert!(timeout.is_retryable());
+
+    let refused = Error::from(PacketError::Io(IOError::from(
+        IOErrorKind::ConnectionRefused,
+    )));
+    assert_eq!(refused.kind(), ErrorKind::Network);
+    assert!(!refused.is_retryable());
+
+    let crc = Error::from(PacketError::Crc32(1, 2));
+    assert_eq!(crc.kind(), ErrorKind::Checksum);
+}
+
+#[test]
+fn challenge_required() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server_addr = server.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut buf = [0; 1400];
+        let (_, client) = server.recv_from(&mut buf).unwrap();
+        server
+            .send_to(b"\xFF\xFF\xFF\xFFA\x01\x02\x03\x04", client)
+            .unwrap();
+    });
+
+    let query = ValveQuery::<SourceParser>::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    query.set_timeout(Some(Duration::new(5, 0))).unwrap();
+    query.connect(server_addr).unwrap();
+    let err = Error::from(query.a2s_players(0xFFFF_FFFF).unwrap_err());
+    assert!(matches!(
+        err,
+        Error::Server(vquery::server::Error::ChallengeRequired(0x0403_0201))
+    ));
+    assert_eq!(err.kind(), ErrorKind::ChallengeRequired);
+    assert!(err.is_retryable());
+}
+
+#[test]
+fn malformed_reply() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server_addr = server.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut buf = [0; 1400];
+        let (_, client) = server.recv_from(&mut buf).unwrap();
+        server.send_to(b"\xFF\xFF\xFF\xFFI\x11", client).unwrap();
+    });
+
+    let query = ValveQuery::<SourceParser>::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    query.set_timeout(Some(Duration::new(5, 0))).unwrap();
+    query.connect(server_addr).unwrap();
+    let err = Error::from(query.a2s_info_new().unwrap_err());
+    assert_eq!(err.kind(), ErrorKind::MalformedReply);
+    assert!(!err.is_retryable());
+    match err {
+        Error::Server(vquery::server::Error::A2SParse(parse)) => {
+            assert_eq!(parse.message, "A2S_INFO");
+            assert_eq!(parse.field, "name");
+            assert_eq!(parse.offset, 2);
+            assert_eq!(parse.kind, None);
+            assert_eq!(parse.excerpt, "0x0000: 49 11 [end]");
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn nested_field_diagnostics() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server_addr = server.local_addr().unwrap();
+    thread::spawn(move || {
+        let mut buf = [0; 1400];
+        let (_, client) = server.recv_from(&mut buf).unwrap();
+        let mut reply = b"\xFF\xFF\xFF\xFFI\x11name\0map\0folder\0game\0".to_vec();
+        reply.extend(&[0xF0, 0x00, 1, 2, 0, b'd', b'l', 0, 1]);
+        reply.extend(b"1.0\0\x20keywo");
+        server.send_to(&reply, client).unwrap();
+    });
+
+    let query = ValveQuery::<SourceParser>::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    query.set_timeout(Some(Duration::new(5, 0))).unwrap();
+    query.connect(server_addr).unwrap();
+    match query.a2s_info_new().unwrap_err() {
+        vquery::server::Error::A2SParse(parse) => {
+            assert_eq!(parse.field, "extra_data.keywords");
+            assert_eq!(parse.offset, 42);
+            assert!(parse.to_string().contains("`extra_data.keywords`"));
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn indexed_field_path() {
+    let mut data = vec![2, 0];
+    data.extend(b"first\0");
+    data.extend(&[0; 8]);
+    data.extend(b"\x01sec");
+    match PlayersList::parse(&data, THE_SHIP_APP_ID).unwrap_err() {
+        nom::Err::Error(err) => {
+            assert_eq!(err.path(), "players[1].name");
+            assert_eq!(err.kind, None);
+            assert!(err.input.is_empty());
+        }
+        err => panic!("unexpected error: {:?}", err),
+    }
+}
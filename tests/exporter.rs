@@ -0,0 +1,113 @@
+#![cfg(feature = "exporter")]
+
+mod common;
+
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+    time::Duration,
+};
+use vquery::exporter::{Exporter, Target};
+
+#[test]
+fn render_metrics() {
+    let alive = common::mock_server(|_| vec![common::info_reply("de_dust2", 5)]);
+    let dead = common::dead_address();
+    let exporter = Exporter::new(
+        vec![Target::source(alive), Target::goldsrc(dead)],
+        Duration::from_secs(15),
+        Duration::from_millis(200),
+    );
+    exporter.poll();
+    exporter.poll();
+
+    let metrics = exporter.render();
+    let alive = format!("server=\"{}\"", alive);
+    let dead = format!("server=\"{}\"", dead);
+    assert!(metrics.contains(&format!("vquery_up{{{}}} 1\n", alive)));
+    assert!(metrics.contains(&format!("vquery_up{{{}}} 0\n", dead)));
+    assert!(metrics.contains(&format!("vquery_players{{{}}} 5\n", alive)));
+    assert!(metrics.contains(&format!("vquery_max_players{{{}}} 16\n", alive)));
+    assert!(metrics.contains(&format!("vquery_bots{{{}}} 1\n", alive)));
+    assert!(metrics.contains(&format!(
+        "vquery_map_info{{{},map=\"de_dust2\"}} 1\n",
+        alive
+    )));
+    assert!(metrics.contains(&format!("vquery_query_failures_total{{{}}} 0\n", alive)));
+    assert!(metrics.contains(&format!("vquery_query_failures_total{{{}}} 2\n", dead)));
+    assert!(!metrics.contains(&format!("vquery_players{{{}}}", dead)));
+}
+
+#[test]
+fn serve_metrics() {
+    let server = common::mock_server(|_| vec![common::info_reply("cp_badlands", 3)]);
+    let listen = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    thread::spawn(move || {
+        Exporter::new(
+            vec![Target::source(server)],
+            Duration::from_secs(15),
+            Duration::from_secs(1),
+        )
+        .serve(listen)
+        .unwrap()
+    });
+
+    for _ in 0..50 {
+        if let Ok(mut stream) = TcpStream::connect(listen) {
+            stream
+                .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            assert!(response.starts_with("HTTP/1.1 200 OK"));
+            if response.contains("map=\"cp_badlands\"") {
+                return;
+            }
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    panic!("exporter didn't serve metrics");
+}
+
+#[test]
+fn stalled_scrape() {
+    let listen = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    thread::spawn(move || {
+        let mut exporter = Exporter::new(
+            vec![Target::source(common::dead_address())],
+            Duration::from_secs(15),
+            Duration::from_millis(100),
+        );
+        exporter.set_scrape_timeout(Duration::from_millis(200));
+        exporter.serve(listen).unwrap()
+    });
+
+    let mut stalled = None;
+    for _ in 0..50 {
+        if let Ok(stream) = TcpStream::connect(listen) {
+            stalled = Some(stream);
+            break;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    // Never sends a request
+    let _stalled = stalled.expect("exporter didn't start");
+
+    let mut stream = TcpStream::connect(listen).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::{info_reply, mock_server};
+use std::{
+    net::SocketAddr,
+    thread,
+    time::{Duration, Instant},
+};
+use vquery::{
+    master::{Region, ServersQuery},
+    server::{SourceParser, ValveQuery},
+};
+
+#[test]
+fn server_failover() {
+    let silent = mock_server(|_| vec![]);
+    let alive = mock_server(|_| vec![info_reply("de_dust2", 3)]);
+
+    let query = ValveQuery::<SourceParser>::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    query.set_timeout(Some(Duration::from_millis(200))).unwrap();
+    query.connect(&[silent, alive][..]).unwrap();
+    assert_eq!(query.transport().peer_addr().unwrap(), silent);
+    assert_eq!(query.a2s_info_new().unwrap().players, 3);
+
+    // Stays with the address which answered
+    assert_eq!(query.transport().peer_addr().unwrap(), alive);
+    assert_eq!(query.a2s_info_new().unwrap().players, 3);
+    assert_eq!(query.transport().peer_addr().unwrap(), alive);
+}
+
+#[test]
+fn master_failover() {
+    let silent = mock_server(|_| vec![]);
+    let alive = mock_server(|_| vec![b"\xFF\xFF\xFF\xFF\x66\x0A\x0A\x00\x00\x01\x69\x87".to_vec()]);
+
+    let query = ServersQuery::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    query.set_timeout(Some(Duration::from_millis(200))).unwrap();
+    query.connect(&[silent, alive][..]).unwrap();
+    let servers = query
+        .request(&"0.0.0.0:0".parse().unwrap(), Region::All, &[])
+        .unwrap();
+    assert_eq!(servers, ["10.0.0.1:27015".parse().unwrap()]);
+    assert_eq!(query.transport().peer_addr().unwrap(), alive);
+}
+
+#[test]
+fn connect_during_request() {
+    let silent = mock_server(|_| vec![]);
+    let alive = mock_server(|_| vec![info_reply("de_dust2", 3)]);
+
+    let query = ValveQuery::<SourceParser>::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    query.set_timeout(Some(Duration::from_secs(1))).unwrap();
+    query.connect(silent).unwrap();
+    thread::scope(|scope| {
+        scope.spawn(|| query.a2s_info_new());
+        thread::sleep(Duration::from_millis(100));
+        // Pending request doesn't hold the addresses until it times out
+        let start = Instant::now();
+        query.connect(alive).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(500));
+    });
+}
+
+#[test]
+fn no_addresses() {
+    let query = ValveQuery::<SourceParser>::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let addresses: [SocketAddr; 0] = [];
+    assert!(query.connect(&addresses[..]).is_err());
+
+    // IPv6 addresses are skipped by an IPv4 socket
+    assert!(query.connect("[::1]:27015").is_err());
+}
@@ -0,0 +1,205 @@
+use vquery::master::*;
+
+#[test]
+fn parse_filters_round_trip() {
+    let filters = vec![
+        Filter::Appid("730".into()),
+        Filter::NotEmpty,
+        Filter::Nor(vec![Filter::Map("de_dust2".into()), Filter::Linux]),
+        Filter::NoPassword,
+        Filter::GameAddr("1.2.3.4:27015".parse().unwrap()),
+        Filter::CollapseAddrHash,
+    ];
+    let wire: String = filters.iter().map(|f| f.to_string()).collect();
+    assert_eq!(
+        wire,
+        "\\appid\\730\\empty\\1\\nor\\2\\map\\de_dust2\\linux\\1\\password\\0\\gameaddr\\1.2.3.4:27015\\collapse_addr_hash\\1"
+    );
+    assert_eq!(parse_filters(&wire), Some(filters));
+    assert_eq!(parse_filters(""), Some(vec![]));
+}
+
+#[test]
+fn parse_invalid_filters() {
+    assert_eq!(parse_filters("appid\\730"), None);
+    assert_eq!(parse_filters("\\appid"), None);
+    assert_eq!(parse_filters("\\nor\\2\\linux\\1"), None);
+    assert_eq!(parse_filters("\\unknown\\1"), None);
+    assert_eq!(parse_filters("\\collaspse_addr_hash\\1"), None);
+
+    // Nesting of groups is bounded
+    let nested = |depth: usize| "\\nor\\1".repeat(depth) + "\\linux\\1";
+    assert!(parse_filters(&nested(8)).is_some());
+    assert_eq!(parse_filters(&nested(9)), None);
+    assert_eq!(parse_filters(&nested(100_000)), None);
+}
+
+#[test]
+fn filter_builder() {
+    let filters = FilterBuilder::new()
+        .app_id(440)
+        .not_app_id(730)
+        .dedicated()
+        .game_ip("1.2.3.4".parse().unwrap())
+        .game_data_any(&["coop", "versus"])
+        .nand(FilterBuilder::new().not_full().secure())
+        .build()
+        .unwrap();
+    let wire: String = filters.iter().map(|f| f.to_string()).collect();
+    assert_eq!(
+        wire,
+        "\\appid\\440\\napp\\730\\dedicated\\1\\gameaddr\\1.2.3.4\\gamedataor\\coop,versus\\nand\\2\\full\\1\\secure\\1"
+    );
+    assert_eq!(parse_filters(&wire), Some(filters));
+}
+
+#[test]
+fn filter_builder_validation() {
+    assert_eq!(
+        FilterBuilder::new().nor(FilterBuilder::new()).build(),
+        Err(FilterError::EmptyGroup("nor"))
+    );
+    assert_eq!(
+        FilterBuilder::new().map("").build(),
+        Err(FilterError::EmptyValue("map"))
+    );
+    assert!(matches!(
+        FilterBuilder::new()
+            .nand(FilterBuilder::new().name_match("a\\b"))
+            .build(),
+        Err(FilterError::ReservedChar {
+            key: "name_match",
+            ..
+        })
+    ));
+    assert!(matches!(
+        FilterBuilder::new().game_type(&["a,b"]).build(),
+        Err(FilterError::ReservedChar {
+            key: "gametype",
+            ..
+        })
+    ));
+    assert_eq!(
+        FilterBuilder::new().game_type(&[]).build(),
+        Err(FilterError::EmptyValue("gametype"))
+    );
+    let long_name = "x".repeat(MAX_FILTER_LEN);
+    assert!(matches!(
+        FilterBuilder::new().name_match(&long_name).to_wire(),
+        Err(FilterError::TooLong(_))
+    ));
+
+    // Hand-made filters are checked the same way
+    assert_eq!(
+        validate_filters(&[Filter::Nand(vec![])]),
+        Err(FilterError::EmptyGroup("nand"))
+    );
+    assert!(validate_filters(&[Filter::Map("de_dust2".into())]).is_ok());
+}
+
+fn info() -> vquery::server::InfoNew {
+    let mut reply = b"I\x11Test server\0de_dust2\0csgo\0Counter-Strike\0".to_vec();
+    reply.extend(&730_i16.to_le_bytes());
+    reply.extend(&[3, 16, 1, b'd', b'l', 0, 1]);
+    reply.extend(b"1.0.4\0\x20secure,valve_ds,cs\0");
+    vquery::server::decode_info_new(&reply).unwrap()
+}
+
+#[test]
+fn match_info() {
+    let info = info();
+    let matches = |wire: &str| {
+        parse_filters(wire)
+            .unwrap()
+            .iter()
+            .all(|filter| filter.matches(&info))
+    };
+    assert!(matches(
+        "\\appid\\730\\napp\\440\\dedicated\\1\\linux\\1\\secure\\1\\password\\0"
+    ));
+    assert!(matches("\\empty\\1\\full\\1\\gamedir\\CSGO\\map\\de_dust2"));
+    assert!(!matches("\\noplayers\\1"));
+    assert!(!matches("\\appid\\440"));
+    assert!(!matches("\\proxy\\1"));
+
+    assert!(matches("\\gametype\\valve_ds,secure"));
+    assert!(!matches("\\gametype\\valve_ds,insecure"));
+    assert!(matches("\\name_match\\test*"));
+    assert!(matches("\\name_match\\*SERV*"));
+    assert!(!matches("\\name_match\\*serv"));
+    assert!(matches("\\version_match\\1.0.*"));
+    assert!(!matches("\\version_match\\1.1.*"));
+
+    assert!(matches("\\nor\\2\\map\\de_nuke\\proxy\\1"));
+    assert!(!matches("\\nor\\2\\map\\de_nuke\\linux\\1"));
+    assert!(matches("\\nand\\2\\map\\de_dust2\\noplayers\\1"));
+    assert!(!matches("\\nand\\2\\map\\de_dust2\\empty\\1"));
+
+    // Unknown filters don't reject servers, neither directly nor in groups
+    assert!(matches("\\white\\1"));
+    assert!(matches("\\nor\\1\\white\\1"));
+    assert!(!matches("\\nor\\2\\white\\1\\map\\de_dust2"));
+    assert!(matches("\\nand\\2\\white\\1\\map\\de_dust2"));
+}
+
+#[test]
+fn match_large_app_id() {
+    let reply = |gameid: Option<u64>| {
+        let mut reply = b"I\x11name\0map\0folder\0game\0".to_vec();
+        // `steamid` holds only the lower 16 bits of 70000
+        reply.extend(&(70000_u32 as i16).to_le_bytes());
+        reply.extend(&[3, 16, 1, b'd', b'l', 0, 1]);
+        reply.extend(b"1.0\0");
+        match gameid {
+            Some(gameid) => {
+                reply.push(0x01);
+                reply.extend(&gameid.to_le_bytes());
+            }
+            None => reply.push(0),
+        }
+        vquery::server::decode_info_new(&reply).unwrap()
+    };
+
+    let info = reply(Some(70000));
+    assert!(Filter::Appid("70000".into()).matches(&info));
+    assert!(!Filter::Appid("4464".into()).matches(&info));
+    assert!(!Filter::NotAppid("70000".into()).matches(&info));
+
+    // Without game id the app id is unknown, unless it fits into `steamid`
+    let info = reply(None);
+    assert!(Filter::Appid("70000".into()).matches(&info));
+    assert!(Filter::NotAppid("70000".into()).matches(&info));
+    assert!(Filter::Appid("4464".into()).matches(&info));
+    assert!(!Filter::Appid("730".into()).matches(&info));
+    assert!(!Filter::Appid("app".into()).matches(&info));
+}
+
+#[test]
+fn match_info_old() {
+    let info = vquery::server::InfoOld {
+        address: std::ffi::CString::new("10.0.0.1:27015").unwrap(),
+        name: std::ffi::CString::new("Half-Life").unwrap(),
+        map: std::ffi::CString::new("crossfire").unwrap(),
+        folder: std::ffi::CString::new("valve").unwrap(),
+        game: std::ffi::CString::new("Half-Life").unwrap(),
+        players: 0,
+        max_players: 8,
+        protocol: 47,
+        server_type: b'D',
+        enviroment: b'W',
+        is_private: true,
+        mod_data: None,
+        vac_secured: false,
+        bots_num: 0,
+    };
+    let matches = |filter: Filter| filter.matches_old(&info);
+    assert!(matches(Filter::Dedicated));
+    assert!(matches(Filter::NoPlayers));
+    assert!(!matches(Filter::Linux));
+    assert!(!matches(Filter::NoPassword));
+    assert!(matches(Filter::GameAddr("10.0.0.1:27015".parse().unwrap())));
+    assert!(!matches(Filter::GameIp("10.0.0.2".parse().unwrap())));
+    // There're no app id and tags in the obsolete reply
+    assert!(matches(Filter::Appid("70".into())));
+    assert!(matches(Filter::GameType("secure".into())));
+}
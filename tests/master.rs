@@ -1,6 +1,6 @@
 use std::{
     collections::HashSet,
-    net::{Ipv4Addr, SocketAddrV4, ToSocketAddrs},
+    net::{Ipv4Addr, SocketAddrV4},
 };
 use vquery::master::*;
 
@@ -9,9 +9,7 @@ const ADDR: &str = "hl2master.steampowered.com:27011";
 #[test]
 fn print_query_iter() {
     let master = ServersQuery::bind("0.0.0.0:0".parse().unwrap()).unwrap();
-    master
-        .connect(ADDR.to_socket_addrs().unwrap().next().unwrap())
-        .unwrap();
+    master.connect(ADDR).unwrap();
 
     let ips: Vec<_> = master
         .iter(Region::All, &[])
@@ -26,9 +24,7 @@ fn validate_query_iter() {
     let nul_addr = SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 0);
 
     let master = ServersQuery::bind("0.0.0.0:0".parse().unwrap()).unwrap();
-    master
-        .connect(ADDR.to_socket_addrs().unwrap().next().unwrap())
-        .unwrap();
+    master.connect(ADDR).unwrap();
 
     let ips: Vec<_> = master
         .iter(Region::All, &[])
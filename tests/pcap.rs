@@ -0,0 +1,142 @@
+#![cfg(feature = "pcap")]
+mod common;
+
+use common::info_reply;
+use vquery::{
+    pcap::{replay, Error, Reply},
+    server::SourceParser,
+    ErrorKind,
+};
+
+const SERVER: [u8; 4] = [10, 0, 0, 1];
+const CLIENT: [u8; 4] = [10, 0, 0, 2];
+
+/// Ethernet frame with IPv4 and UDP headers.
+fn frame(source: ([u8; 4], u16), destination: ([u8; 4], u16), payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0; 12];
+    frame.extend(&[0x08, 0x00]);
+    frame.extend(&[0x45, 0]);
+    frame.extend(&(20 + 8 + payload.len() as u16).to_be_bytes());
+    frame.extend(&[0, 0, 0x40, 0, 64, 17, 0, 0]);
+    frame.extend(&source.0);
+    frame.extend(&destination.0);
+    frame.extend(&source.1.to_be_bytes());
+    frame.extend(&destination.1.to_be_bytes());
+    frame.extend(&(8 + payload.len() as u16).to_be_bytes());
+    frame.extend(&[0, 0]);
+    frame.extend(payload);
+    frame
+}
+
+fn pcap(frames: &[Vec<u8>]) -> Vec<u8> {
+    let mut capture = 0xA1B2_C3D4_u32.to_le_bytes().to_vec();
+    capture.extend(&[2, 0, 4, 0]);
+    capture.extend(&[0; 8]);
+    capture.extend(&65535_u32.to_le_bytes());
+    capture.extend(&1_u32.to_le_bytes());
+    for (second, frame) in frames.iter().enumerate() {
+        capture.extend(&(second as u32).to_le_bytes());
+        capture.extend(&500_u32.to_le_bytes());
+        capture.extend(&(frame.len() as u32).to_le_bytes());
+        capture.extend(&(frame.len() as u32).to_le_bytes());
+        capture.extend(frame);
+    }
+    capture
+}
+
+fn pcapng_block(block_type: u32, body: &[u8]) -> Vec<u8> {
+    let mut body = body.to_vec();
+    body.resize(body.len().div_ceil(4) * 4, 0);
+    let len = (body.len() as u32 + 12).to_le_bytes();
+    let mut block = block_type.to_le_bytes().to_vec();
+    block.extend(&len);
+    block.extend(body);
+    block.extend(&len);
+    block
+}
+
+fn pcapng(frames: &[Vec<u8>]) -> Vec<u8> {
+    let mut section = 0x1A2B_3C4D_u32.to_le_bytes().to_vec();
+    section.extend(&[1, 0, 0, 0]);
+    section.extend(&u64::MAX.to_le_bytes());
+    let mut capture = pcapng_block(0x0A0D_0D0A, &section);
+    let mut interface = vec![1, 0, 0, 0];
+    interface.extend(&65535_u32.to_le_bytes());
+    // Timestamps in milliseconds
+    interface.extend(&[9, 0, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0]);
+    capture.extend(pcapng_block(1, &interface));
+    for (second, frame) in frames.iter().enumerate() {
+        let mut packet = 0_u32.to_le_bytes().to_vec();
+        packet.extend(&0_u32.to_le_bytes());
+        packet.extend(&(second as u32 * 1000).to_le_bytes());
+        packet.extend(&(frame.len() as u32).to_le_bytes());
+        packet.extend(&(frame.len() as u32).to_le_bytes());
+        packet.extend(frame);
+        capture.extend(pcapng_block(6, &packet));
+    }
+    capture
+}
+
+fn source_packet(total: u8, index: u8, payload: &[u8]) -> Vec<u8> {
+    let mut packet = b"\xFE\xFF\xFF\xFF\x07\x00\x00\x00".to_vec();
+    packet.extend(&[total, index]);
+    packet.extend(&1248_u16.to_le_bytes());
+    packet.extend(payload);
+    packet
+}
+
+fn traffic() -> Vec<Vec<u8>> {
+    let server = (SERVER, 27015);
+    let client = (CLIENT, 50000);
+    let rules = b"E\x02\x00sv_gravity\x00800\x00mp_timelimit\x0030\x00";
+    let mut broken = info_reply("de_dust2", 1);
+    broken.truncate(20);
+    vec![
+        frame(client, server, b"\xFF\xFF\xFF\xFFTSource Engine Query\x00"),
+        frame(server, client, &info_reply("de_dust2", 1)),
+        frame(server, client, &source_packet(2, 0, &rules[..10])),
+        frame(server, client, &source_packet(2, 1, &rules[10..])),
+        frame(server, client, &broken),
+        frame(server, client, b"\xFF\xFF\xFF\xFFZunknown"),
+        // Unrelated traffic
+        frame(([10, 0, 0, 3], 53), client, b"\xFF\xFF\xFF\xFFI"),
+    ]
+}
+
+#[test]
+fn replay_pcap() {
+    for capture in &[pcap(&traffic()), pcapng(&traffic())] {
+        let records = replay::<SourceParser>(capture, &[27015]).unwrap();
+        assert_eq!(records.len(), 4);
+        assert!(records
+            .iter()
+            .all(|record| record.server == "10.0.0.1:27015".parse().unwrap()));
+        assert!(matches!(&records[0].reply, Ok(Reply::InfoNew(info)) if info.players == 1));
+        assert!(matches!(&records[1].reply, Ok(Reply::Rules(list)) if list.rules.len() == 2));
+        assert_eq!(records[1].timestamp.as_secs(), 3);
+        assert_eq!(
+            records[2].reply.as_ref().unwrap_err().kind(),
+            ErrorKind::MalformedReply
+        );
+        assert!(matches!(
+            records[3].reply,
+            Err(vquery::Error::Server(vquery::server::Error::UnknownReply(
+                Some(b'Z')
+            )))
+        ));
+    }
+}
+
+#[test]
+fn unknown_format() {
+    assert!(matches!(
+        replay::<SourceParser>(b"not a capture", &[]),
+        Err(Error::UnknownFormat(_))
+    ));
+    let mut capture = pcap(&traffic());
+    capture.truncate(capture.len() - 3);
+    assert!(matches!(
+        replay::<SourceParser>(&capture, &[]),
+        Err(Error::Truncated(_))
+    ));
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use common::{master_reply, master_seed as seed, mock_server};
+use std::time::Duration;
+use vquery::{
+    master::{MasterPool, PoolMode, Region},
+    ErrorKind,
+};
+
+#[test]
+fn parallel_merge() {
+    let first = mock_server(|request| match seed(request) {
+        "0.0.0.0:0" => vec![master_reply(&["10.0.0.1:27015", "10.0.0.2:27015"], false)],
+        _ => vec![master_reply(&["10.0.0.2:27015", "10.0.0.3:27015"], true)],
+    });
+    let second = mock_server(|_| vec![master_reply(&["10.0.0.3:27015", "10.0.0.4:27015"], true)]);
+    let silent = mock_server(|_| vec![]);
+
+    let mut pool = MasterPool::new(vec![first, second, silent]);
+    pool.set_timeout(Duration::from_millis(300));
+    let crawl = pool.crawl(Region::All, &[]);
+
+    let servers: Vec<_> = crawl.servers().iter().map(|s| s.to_string()).collect();
+    assert_eq!(
+        servers,
+        [
+            "10.0.0.1:27015",
+            "10.0.0.2:27015",
+            "10.0.0.3:27015",
+            "10.0.0.4:27015"
+        ]
+    );
+    let reports = crawl.reports();
+    assert_eq!(reports[0].pages, 2);
+    assert_eq!(reports[0].servers.len(), 3);
+    assert!(reports[1].error.is_none());
+    assert_eq!(
+        reports[2].error.as_ref().map(|err| err.kind()),
+        Some(ErrorKind::Timeout)
+    );
+    assert_eq!(
+        crawl.sources(&"10.0.0.3:27015".parse().unwrap()),
+        [first, second]
+    );
+    assert!(crawl.sources(&"10.0.0.9:27015".parse().unwrap()).is_empty());
+    assert!(!crawl.is_failed());
+    assert_eq!(crawl.into_iter().count(), 4);
+}
+
+#[test]
+fn failover() {
+    let silent = mock_server(|_| vec![]);
+    let alive = mock_server(|_| vec![master_reply(&["10.0.0.1:27015"], true)]);
+    let unused = mock_server(|_| vec![master_reply(&["10.0.0.9:27015"], true)]);
+
+    let mut pool = MasterPool::new(vec![silent, alive, unused]);
+    pool.set_mode(PoolMode::Failover);
+    pool.set_timeout(Duration::from_millis(300));
+    let crawl = pool.crawl(Region::All, &[]);
+
+    assert_eq!(crawl.servers(), ["10.0.0.1:27015".parse().unwrap()]);
+    let masters: Vec<_> = crawl.reports().iter().map(|r| r.master).collect();
+    assert_eq!(masters, [silent, alive]);
+    assert!(crawl.reports()[0].error.is_some());
+}
+
+#[test]
+fn seed_cycle() {
+    // Second page points back to the first one
+    let master = mock_server(|request| match seed(request) {
+        "0.0.0.0:0" => vec![master_reply(&["10.0.0.1:27015", "10.0.0.2:27015"], false)],
+        _ => vec![master_reply(&["10.0.0.2:27015", "10.0.0.1:27015"], false)],
+    });
+
+    let mut pool = MasterPool::new(vec![master]);
+    pool.set_timeout(Duration::from_millis(300));
+    let crawl = pool.crawl(Region::All, &[]);
+    let report = &crawl.reports()[0];
+    assert_eq!(report.pages, 3);
+    assert_eq!(report.servers.len(), 2);
+    assert_eq!(
+        report.error.as_ref().map(|err| err.kind()),
+        Some(ErrorKind::MalformedReply)
+    );
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::{info_reply, mock_server};
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use vquery::{
+    server::{SourceParser, ValveQuery},
+    Limit, RateLimiter,
+};
+
+#[test]
+fn buckets() {
+    let first: SocketAddr = "10.0.0.1:27015".parse().unwrap();
+    let second: SocketAddr = "10.0.0.2:27015".parse().unwrap();
+    let limiter = RateLimiter::new(Some(Limit::new(1.0, 3)), Some(Limit::new(1.0, 2)));
+
+    assert!(limiter.try_acquire(first).is_ok());
+    assert!(limiter.try_acquire(first).is_ok());
+    // Per-destination burst is exhausted
+    let wait = limiter.try_acquire(first).unwrap_err();
+    assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1));
+
+    assert!(limiter.try_acquire(second).is_ok());
+    // Global burst is exhausted
+    assert!(limiter.try_acquire(second).is_err());
+}
+
+#[test]
+fn limited_queries() {
+    let address = mock_server(|_| vec![info_reply("de_dust2", 0)]);
+    let mut query = ValveQuery::<SourceParser>::bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    query.set_timeout(Some(Duration::from_secs(1))).unwrap();
+    query.connect(address).unwrap();
+    query.set_rate_limiter(Some(Arc::new(RateLimiter::new(
+        None,
+        Some(Limit::new(10.0, 1)),
+    ))));
+
+    let start = Instant::now();
+    for _ in 0..3 {
+        query.a2s_info_new().unwrap();
+    }
+    assert!(start.elapsed() >= Duration::from_millis(190));
+}
+
+#[test]
+fn tiny_rate() {
+    let day = Duration::from_secs(86400);
+    for &rate in &[1e-300, 0.0, -1.0, f64::NAN] {
+        let limit = Limit::new(rate, 0);
+        assert_eq!((limit.rate(), limit.burst()), (1.0 / 86400.0, 1));
+        let limiter = RateLimiter::new(Some(limit), None);
+        let address: SocketAddr = "10.0.0.1:27015".parse().unwrap();
+        assert!(limiter.try_acquire(address).is_ok());
+        let wait = limiter.try_acquire(address).unwrap_err();
+        assert!(wait > day - Duration::from_secs(1) && wait <= day);
+    }
+}
@@ -0,0 +1,203 @@
+mod common;
+
+use common::info_reply;
+use vquery::{
+    server::{decode_info_new, GoldsrcParser, PacketError, Progress, Reassembler, SourceParser},
+    ErrorKind, LimitKind, Limits,
+};
+
+fn source_packet(uid: u32, total: u8, index: u8, payload: &[u8]) -> Vec<u8> {
+    let mut packet = b"\xFE\xFF\xFF\xFF".to_vec();
+    packet.extend(&uid.to_le_bytes());
+    packet.extend(&[total, index]);
+    packet.extend(&1248_u16.to_le_bytes());
+    packet.extend(payload);
+    packet
+}
+
+fn goldsrc_packet(uid: u32, total: u8, index: u8, payload: &[u8]) -> Vec<u8> {
+    let mut packet = b"\xFE\xFF\xFF\xFF".to_vec();
+    packet.extend(&uid.to_le_bytes());
+    packet.push(index << 4 | total);
+    packet.extend(payload);
+    packet
+}
+
+#[test]
+fn single_packet() {
+    let mut reassembler = Reassembler::<SourceParser>::new();
+    let reply = info_reply("de_dust2", 4);
+    let payload = match reassembler.feed(&reply).unwrap() {
+        Progress::Complete(payload) => payload,
+        Progress::NeedMore => panic!("single packet is complete"),
+    };
+    assert_eq!(decode_info_new(&payload).unwrap().players, 4);
+}
+
+#[test]
+fn out_of_order_source_packets() {
+    let reply = info_reply("de_dust2", 4);
+    let (first, second) = reply[4..].split_at(10);
+    let mut reassembler = Reassembler::<SourceParser>::new();
+    assert_eq!(
+        reassembler.feed(&source_packet(7, 2, 1, second)).unwrap(),
+        Progress::NeedMore
+    );
+    // Duplicates are ignored
+    assert_eq!(
+        reassembler.feed(&source_packet(7, 2, 1, second)).unwrap(),
+        Progress::NeedMore
+    );
+    assert_eq!(
+        reassembler.feed(&source_packet(7, 2, 0, first)).unwrap(),
+        Progress::Complete(reply[4..].to_vec())
+    );
+}
+
+#[test]
+fn goldsrc_packets() {
+    let mut reassembler = Reassembler::<GoldsrcParser>::new();
+    assert_eq!(
+        reassembler.feed(&goldsrc_packet(1, 3, 0, b"ab")).unwrap(),
+        Progress::NeedMore
+    );
+    assert_eq!(
+        reassembler.feed(&goldsrc_packet(1, 3, 2, b"ef")).unwrap(),
+        Progress::NeedMore
+    );
+    assert_eq!(
+        reassembler.feed(&goldsrc_packet(1, 3, 1, b"cd")).unwrap(),
+        Progress::Complete(b"abcdef".to_vec())
+    );
+}
+
+#[test]
+fn broken_packets() {
+    let mut reassembler = Reassembler::<SourceParser>::new();
+    assert!(matches!(
+        reassembler.feed(&source_packet(1, 2, 2, b"ab")),
+        Err(PacketError::WrongIndex { index: 2, total: 2 })
+    ));
+    assert!(matches!(
+        reassembler.feed(&source_packet(1, 0, 0, b"ab")),
+        Err(PacketError::WrongIndex { index: 0, total: 0 })
+    ));
+
+    reassembler.feed(&source_packet(1, 2, 0, b"ab")).unwrap();
+    assert!(matches!(
+        reassembler.feed(&source_packet(2, 2, 1, b"cd")),
+        Err(PacketError::Interrupted { .. })
+    ));
+    // Error resets the state, so a new reply can be fed
+    reassembler.feed(&source_packet(2, 2, 1, b"cd")).unwrap();
+    assert_eq!(
+        reassembler.feed(&source_packet(2, 2, 0, b"ab")).unwrap(),
+        Progress::Complete(b"abcd".to_vec())
+    );
+}
+
+fn compressed_packets(payload: &[u8], decompressed_size: u32) -> Vec<Vec<u8>> {
+    use bzip2::{write::BzEncoder, Compression};
+    use std::io::Write;
+
+    let mut encoder = BzEncoder::new(vec![], Compression::best());
+    encoder.write_all(payload).unwrap();
+    let compressed = encoder.finish().unwrap();
+    let (first, second) = compressed.split_at(compressed.len() / 2);
+
+    let mut packet = b"\xFE\xFF\xFF\xFF\x08\x00\x00\x80\x02\x00".to_vec();
+    packet.extend(&1248_u16.to_le_bytes());
+    packet.extend(&decompressed_size.to_le_bytes());
+    packet.extend(&crc::crc32::checksum_ieee(payload).to_le_bytes());
+    packet.extend(first);
+    vec![packet, source_packet(0x8000_0008, 2, 1, second)]
+}
+
+#[test]
+fn compressed_packets_with_lying_size() {
+    let payload = b"\xFF\xFF\xFF\xFFE\x01\x00sv_gravity\x00800\x00";
+    for &size in &[payload.len() as u32, u32::MAX] {
+        let packets = compressed_packets(payload, size);
+        let mut reassembler = Reassembler::<SourceParser>::new();
+        assert_eq!(reassembler.feed(&packets[0]).unwrap(), Progress::NeedMore);
+        assert_eq!(
+            reassembler.feed(&packets[1]).unwrap(),
+            Progress::Complete(payload.to_vec())
+        );
+    }
+
+    // Decompressed data is cut at the announced size, so the checksum doesn't match
+    let packets = compressed_packets(payload, 4);
+    let mut reassembler = Reassembler::<SourceParser>::new();
+    reassembler.feed(&packets[0]).unwrap();
+    assert!(matches!(
+        reassembler.feed(&packets[1]),
+        Err(PacketError::Crc32(..))
+    ));
+}
+
+fn limit_of(result: Result<Progress, PacketError>) -> LimitKind {
+    let err = result.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+    match err {
+        PacketError::Limit(err) => err.kind,
+        other => panic!("expected exceeded limit, found {:?}", other),
+    }
+}
+
+#[test]
+fn limits() {
+    let limits = Limits {
+        fragments: 2,
+        fragment_size: 64,
+        payload: 80,
+        decompressed: 16,
+        ..Limits::default()
+    };
+    let mut reassembler = Reassembler::<SourceParser>::with_limits(limits);
+    assert_eq!(reassembler.datagram_size(), 65);
+
+    let unlimited = Limits {
+        fragment_size: usize::MAX,
+        ..Limits::default()
+    };
+    let unlimited = Reassembler::<SourceParser>::with_limits(unlimited);
+    assert_eq!(unlimited.datagram_size(), 1400);
+
+    let packet = source_packet(1, 3, 0, b"part");
+    assert_eq!(limit_of(reassembler.feed(&packet)), LimitKind::Fragments);
+
+    let packet = source_packet(1, 2, 0, &[0; 64]);
+    assert_eq!(limit_of(reassembler.feed(&packet)), LimitKind::FragmentSize);
+
+    let packet = source_packet(1, 2, 0, &[0; 48]);
+    assert_eq!(reassembler.feed(&packet).unwrap(), Progress::NeedMore);
+    let packet = source_packet(1, 2, 1, &[0; 48]);
+    assert_eq!(limit_of(reassembler.feed(&packet)), LimitKind::Payload);
+
+    // Announced size is within the limit, but the actual data isn't
+    let payload = b"\xFF\xFF\xFF\xFFE\x01\x00sv_gravity\x00800\x00";
+    let packets = compressed_packets(payload, 8);
+    reassembler.feed(&packets[0]).unwrap();
+    assert!(matches!(
+        reassembler.feed(&packets[1]),
+        Err(PacketError::Crc32(..))
+    ));
+    for &size in &[payload.len() as u32, u32::MAX] {
+        let packets = compressed_packets(payload, size);
+        reassembler.feed(&packets[0]).unwrap();
+        assert_eq!(
+            limit_of(reassembler.feed(&packets[1])),
+            LimitKind::Decompressed
+        );
+    }
+
+    // Defaults fit usual replies
+    let mut reassembler = Reassembler::<SourceParser>::new();
+    let packets = compressed_packets(payload, payload.len() as u32);
+    reassembler.feed(&packets[0]).unwrap();
+    assert_eq!(
+        reassembler.feed(&packets[1]).unwrap(),
+        Progress::Complete(payload.to_vec())
+    );
+}
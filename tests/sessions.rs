@@ -0,0 +1,79 @@
+use std::{
+    ffi::CString,
+    time::{Duration, SystemTime},
+};
+use vquery::server::{Player, PlayersList, SessionEvent, SessionTracker};
+
+fn list(players: &[(&str, i32, u64)]) -> PlayersList {
+    PlayersList {
+        players_num: players.len() as u8,
+        players: players
+            .iter()
+            .map(|&(name, score, duration)| Player {
+                index: 0,
+                name: CString::new(name).unwrap(),
+                score,
+                duration: Duration::from_secs(duration),
+                the_ship: None,
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn rejoin_starts_new_session() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    let mut tracker = SessionTracker::new();
+
+    let events = tracker.update_at(&list(&[("alice", 5, 100), ("bob", 1, 50)]), start);
+    assert_eq!(events.len(), 2);
+    let ids: Vec<_> = tracker.active().iter().map(|s| s.id).collect();
+    assert_eq!(tracker.active()[0].joined, start - Duration::from_secs(100));
+
+    // Bob has rejoined in the meantime, carol is new
+    let later = start + Duration::from_secs(30);
+    let events = tracker.update_at(
+        &list(&[("alice", 7, 131), ("bob", 0, 10), ("carol", 0, 5)]),
+        later,
+    );
+    let active = tracker.active();
+    assert_eq!(active[0].id, ids[0]);
+    assert_eq!(active[0].playtime(), Duration::from_secs(130));
+    assert_ne!(active[1].id, ids[1]);
+
+    let mut joined: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            SessionEvent::Joined(session) => Some(session.name.as_str()),
+            _ => None,
+        })
+        .collect();
+    joined.sort_unstable();
+    assert_eq!(joined, ["bob", "carol"]);
+    let left: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            SessionEvent::Left(session) => Some(session),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(left.len(), 1);
+    assert_eq!((left[0].id, left[0].left), (ids[1], Some(later)));
+}
+
+#[test]
+fn namesakes_are_told_apart() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    let mut tracker = SessionTracker::new();
+    tracker.update_at(&list(&[("unnamed", 3, 200), ("unnamed", 40, 20)]), start);
+    let ids: Vec<_> = tracker.active().iter().map(|s| s.id).collect();
+
+    // Order of players changes, durations keep sessions apart
+    let events = tracker.update_at(
+        &list(&[("unnamed", 41, 80), ("unnamed", 3, 260)]),
+        start + Duration::from_secs(60),
+    );
+    assert!(events.is_empty());
+    let active: Vec<_> = tracker.active().iter().map(|s| (s.id, s.score)).collect();
+    assert_eq!(active, [(ids[1], 41), (ids[0], 3)]);
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::mock_server;
+use std::{env, fs, net::UdpSocket, process, time::Duration};
+use vquery::{
+    master::{Region, ServersQuery},
+    server::{SourceParser, ValveQuery},
+    ErrorKind, Limits, Recording, Replay, Transport,
+};
+
+fn connected(address: std::net::SocketAddr) -> UdpSocket {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+    socket.connect(address).unwrap();
+    socket
+}
+
+fn split_rules_reply() -> Vec<Vec<u8>> {
+    let rules = b"E\x01\x00sv_gravity\x00800\x00";
+    [(0, &rules[..6]), (1, &rules[6..])]
+        .iter()
+        .map(|&(index, part)| {
+            let mut packet = b"\xFE\xFF\xFF\xFF\x01\x00\x00\x00".to_vec();
+            packet.extend(&[2, index]);
+            packet.extend(&1248_u16.to_le_bytes());
+            packet.extend(part);
+            packet
+        })
+        .collect()
+}
+
+#[test]
+fn record_and_replay_rules() {
+    let address = mock_server(|request| match request[5] {
+        0xFF => vec![b"\xFF\xFF\xFF\xFFA\x01\x02\x03\x04".to_vec()],
+        _ => split_rules_reply(),
+    });
+    let path = env::temp_dir().join(format!("vquery-rules-{}.log", process::id()));
+
+    let recording = Recording::create(connected(address), &path).unwrap();
+    let query = ValveQuery::<SourceParser, _>::with_transport(recording);
+    let challenge = query.a2s_rules_challenge().unwrap();
+    assert_eq!(query.a2s_rules(challenge).unwrap().rules.len(), 1);
+    drop(query);
+
+    // Same requests get the same replies without network
+    let query = ValveQuery::<SourceParser, _>::with_transport(Replay::open(&path).unwrap());
+    assert_eq!(query.transport().peer_addr().unwrap(), address);
+    let challenge = query.a2s_rules_challenge().unwrap();
+    let rules = query.a2s_rules(challenge).unwrap();
+    assert_eq!(rules.rules[0].value.to_str().unwrap(), "800");
+    assert!(query.transport().is_finished());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn replay_master_pages() {
+    let log = "peer 127.0.0.1:27011
+0.000100 > 31ff302e302e302e303a300000
+0.010000 < ffffffff660a0a0000016987000000000000
+";
+    let query = ServersQuery::with_transport(Replay::from_reader(log.as_bytes()).unwrap());
+    let servers: Vec<_> = query
+        .iter(Region::All, &[])
+        .map(|server| server.unwrap().to_string())
+        .collect();
+    assert_eq!(servers, ["10.0.0.1:27015"]);
+
+    // Different request than recorded
+    let query = ServersQuery::with_transport(Replay::from_reader(log.as_bytes()).unwrap());
+    assert!(query
+        .request(&"10.0.0.1:27015".parse().unwrap(), Region::All, &[])
+        .is_err());
+}
+
+#[test]
+fn master_reply_limit() {
+    let log = "peer 127.0.0.1:27011
+0.000100 > 31ff302e302e302e303a300000
+0.010000 < ffffffff660a0a0000016987000000000000
+";
+    let mut query = ServersQuery::with_transport(Replay::from_reader(log.as_bytes()).unwrap());
+    query.set_limits(Limits {
+        master_reply: 12,
+        ..Limits::default()
+    });
+    let err = query
+        .request(&"0.0.0.0:0".parse().unwrap(), Region::All, &[])
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+}
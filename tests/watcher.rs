@@ -0,0 +1,136 @@
+mod common;
+
+use common::{dead_address, info_reply, mock_server};
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+use vquery::{
+    server::{EventKind, SourceParser, Watcher},
+    ErrorKind,
+};
+
+fn players_reply(names: &[&str]) -> Vec<u8> {
+    let mut reply = b"\xFF\xFF\xFF\xFFD".to_vec();
+    reply.push(names.len() as u8);
+    for (index, name) in names.iter().enumerate() {
+        reply.push(index as u8);
+        reply.extend(name.as_bytes());
+        reply.push(0);
+        reply.extend(&0_i32.to_le_bytes());
+        reply.extend(&1.0_f32.to_le_bytes());
+    }
+    reply
+}
+
+#[test]
+fn server_changes() {
+    let round = Arc::new(AtomicUsize::new(0));
+    let server_round = Arc::clone(&round);
+    let address = mock_server(move |request| {
+        let round = server_round.load(Ordering::SeqCst);
+        match (request[4], &request[5..]) {
+            (b'T', _) if round == 0 => vec![info_reply("de_dust2", 2)],
+            (b'T', _) => vec![info_reply("de_nuke", 2)],
+            (b'U', b"\xFF\xFF\xFF\xFF") => vec![b"\xFF\xFF\xFF\xFFA\x01\x02\x03\x04".to_vec()],
+            (b'U', _) if round == 0 => vec![players_reply(&["alice", "bob"])],
+            (b'U', _) => vec![players_reply(&["bob", "carol"])],
+            _ => vec![],
+        }
+    });
+
+    let mut watcher = Watcher::<SourceParser>::new(vec![address], Duration::from_secs(1));
+    watcher.set_timeout(Duration::from_millis(500));
+    watcher.set_watch_rules(false);
+    let kinds = |events: Vec<vquery::server::Event>| -> Vec<EventKind> {
+        events.into_iter().map(|event| event.kind).collect()
+    };
+
+    assert_eq!(kinds(watcher.poll()), vec![EventKind::CameOnline]);
+    assert!(watcher.poll().is_empty());
+
+    round.store(1, Ordering::SeqCst);
+    assert_eq!(
+        kinds(watcher.poll()),
+        vec![
+            EventKind::MapChanged {
+                old: "de_dust2".into(),
+                new: "de_nuke".into()
+            },
+            EventKind::PlayerJoined("carol".into()),
+            EventKind::PlayerLeft("alice".into()),
+        ]
+    );
+}
+
+#[test]
+fn offline_server() {
+    let address = dead_address();
+    let mut watcher = Watcher::<SourceParser>::new(vec![address], Duration::from_millis(10));
+    watcher.set_timeout(Duration::from_millis(100));
+
+    let events = watcher.spawn();
+    let event = events.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event.address, address);
+    assert!(matches!(
+        event.kind,
+        EventKind::Unreachable(ErrorKind::Timeout) | EventKind::Unreachable(ErrorKind::Network)
+    ));
+    // Reported only once
+    assert!(events.recv_timeout(Duration::from_millis(500)).is_err());
+}
+
+#[test]
+fn went_offline() {
+    let online = Arc::new(AtomicBool::new(true));
+    let server_online = Arc::clone(&online);
+    let address = mock_server(move |_| {
+        if server_online.load(Ordering::SeqCst) {
+            vec![info_reply("de_dust2", 2)]
+        } else {
+            vec![]
+        }
+    });
+    let mut watcher = Watcher::<SourceParser>::new(vec![address], Duration::from_secs(1));
+    watcher.set_timeout(Duration::from_millis(100));
+    watcher.set_watch_players(false);
+    watcher.set_watch_rules(false);
+
+    assert_eq!(watcher.poll()[0].kind, EventKind::CameOnline);
+    online.store(false, Ordering::SeqCst);
+    assert_eq!(
+        watcher.poll()[0].kind,
+        EventKind::WentOffline(ErrorKind::Timeout)
+    );
+    assert!(watcher.poll().is_empty());
+    online.store(true, Ordering::SeqCst);
+    assert_eq!(watcher.poll()[0].kind, EventKind::CameOnline);
+}
+
+#[test]
+fn stops_on_drop() {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let server_requests = Arc::clone(&requests);
+    let address = mock_server(move |_| {
+        server_requests.fetch_add(1, Ordering::SeqCst);
+        vec![info_reply("de_dust2", 2)]
+    });
+    let mut watcher = Watcher::<SourceParser>::new(vec![address], Duration::from_millis(10));
+    watcher.set_timeout(Duration::from_millis(500));
+    watcher.set_watch_players(false);
+    watcher.set_watch_rules(false);
+
+    let events = watcher.spawn();
+    let event = events.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(event.kind, EventKind::CameOnline);
+    // The server stays the same, so there are no events to notice the drop by
+    drop(events);
+    thread::sleep(Duration::from_millis(100));
+    let polled = requests.load(Ordering::SeqCst);
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(requests.load(Ordering::SeqCst), polled);
+}
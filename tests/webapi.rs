@@ -0,0 +1,168 @@
+#![cfg(feature = "webapi")]
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+use vquery::{
+    master::{Error, Filter, PlainHttp, WebApiQuery, STEAM_API_URL},
+    ErrorKind,
+};
+
+const REPLY: &str = r#"{"response":{"servers":[
+    {"addr":"10.0.0.1:27016","gameport":27015,"steamid":"90000000000000001","name":"Test server",
+     "appid":730,"gamedir":"csgo","version":"1.0","product":"csgo","region":255,"players":3,
+     "max_players":16,"bots":1,"map":"de_dust2","secure":true,"dedicated":true,"os":"l",
+     "gametype":"empty,secure"},
+    {"addr":"10.0.0.2:27015","appid":730}
+]}}"#;
+
+/// Serves one HTTP response and passes the request line to the receiver.
+fn http_server(status: &'static str, body: &'static str) -> (SocketAddr, Receiver<String>) {
+    http_server_on(TcpListener::bind("127.0.0.1:0").unwrap(), status, body)
+}
+
+/// Same as `http_server`, but also passes request headers after the request line.
+fn http_server_on(
+    listener: TcpListener,
+    status: &'static str,
+    body: &'static str,
+) -> (SocketAddr, Receiver<String>) {
+    let address = listener.local_addr().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(&stream);
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        sender.send(request_line).unwrap();
+        // Rest of the request, so the client doesn't write into a closed connection
+        let mut header = String::new();
+        while reader.read_line(&mut header).unwrap() > 2 {
+            sender.send(header.clone()).unwrap();
+            header.clear();
+        }
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+        .unwrap();
+    });
+    (address, receiver)
+}
+
+fn query(address: SocketAddr) -> WebApiQuery<PlainHttp> {
+    let base_url = format!("http://{}/", address);
+    let mut query = WebApiQuery::new(PlainHttp::default(), &base_url, "KEY");
+    query.set_limit(100);
+    query
+}
+
+#[test]
+fn server_list() {
+    let (address, requests) = http_server("200 OK", REPLY);
+    let query = query(address);
+    let filters = [Filter::Appid("730".into()), Filter::NotEmpty];
+    let servers: Vec<_> = query.iter(&filters).map(Result::unwrap).collect();
+
+    assert_eq!(
+        requests.recv().unwrap().trim_end(),
+        "GET /IGameServersService/GetServerList/v1/?key=KEY&limit=100&filter=%5Cappid%5C730%5Cempty%5C1 HTTP/1.0"
+    );
+    assert_eq!(servers.len(), 2);
+    assert_eq!(servers[0].addr, "10.0.0.1:27016".parse().unwrap());
+    assert_eq!(servers[0].gameport, 27015);
+    assert_eq!(servers[0].steamid, Some(90000000000000001));
+    assert_eq!(servers[0].map, "de_dust2");
+    assert_eq!((servers[0].players, servers[0].max_players), (3, 16));
+    assert!(servers[0].secure);
+    assert_eq!(servers[1].name, "");
+    assert_eq!(servers[1].region, -1);
+}
+
+#[test]
+fn errors() {
+    let (address, _requests) = http_server("403 Forbidden", "");
+    let forbidden = query(address);
+    let mut servers = forbidden.iter(&[]);
+    let err = servers.next().unwrap().unwrap_err();
+    assert!(matches!(err, Error::HttpStatus(403)));
+    assert_eq!(err.kind(), ErrorKind::Network);
+    assert!(servers.next().is_none());
+
+    let (address, _requests) = http_server("200 OK", r#"{"response":{}}"#);
+    assert!(query(address).request(&[]).unwrap().is_empty());
+
+    let (address, _requests) = http_server("200 OK", r#"{"response":{"servers":[{"appid":1}]}}"#);
+    let servers = query(address).request(&[]).unwrap();
+    assert!(matches!(servers[0], Err(Error::WebApiField("addr"))));
+
+    let (address, _requests) = http_server("200 OK", "<html>");
+    let err = query(address).request(&[]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MalformedReply);
+
+    // Plain HTTP client can't reach https:// URLs, and the key stays out of the error
+    let query = WebApiQuery::new(PlainHttp::default(), STEAM_API_URL, "SECRET");
+    match query.request(&[]) {
+        Err(Error::Io(err)) => assert!(!err.to_string().contains("SECRET")),
+        other => panic!(
+            "unexpected result: {:?}",
+            other.map(|servers| servers.len())
+        ),
+    }
+}
+
+#[test]
+fn malformed_server() {
+    let (address, _requests) = http_server(
+        "200 OK",
+        r#"{"response":{"servers":[
+            {"addr":"10.0.0.1:27015","appid":730},
+            {"addr":"not an address","appid":730},
+            {"addr":"10.0.0.3:27015","appid":730}
+        ]}}"#,
+    );
+    let query = query(address);
+    let servers: Vec<_> = query.iter(&[]).collect();
+    assert_eq!(servers.len(), 3);
+    assert_eq!(
+        servers[0].as_ref().unwrap().addr,
+        "10.0.0.1:27015".parse().unwrap()
+    );
+    assert!(matches!(servers[1], Err(Error::WebApiField("addr"))));
+    assert_eq!(
+        servers[2].as_ref().unwrap().addr,
+        "10.0.0.3:27015".parse().unwrap()
+    );
+}
+
+#[test]
+fn ipv6_host() {
+    let listener = match TcpListener::bind("[::1]:0") {
+        Ok(listener) => listener,
+        // No IPv6 on this machine
+        Err(_) => return,
+    };
+    let (address, requests) = http_server_on(listener, "200 OK", r#"{"response":{}}"#);
+    assert!(query(address).request(&[]).unwrap().is_empty());
+    requests.recv().unwrap();
+    assert_eq!(
+        requests.recv().unwrap().trim_end(),
+        format!("Host: [::1]:{}", address.port())
+    );
+
+    // Default port of a bracketed literal, so it's connected to instead of being rejected
+    let query = WebApiQuery::new(PlainHttp::default(), "http://[::1]", "KEY");
+    match query.request(&[]) {
+        Err(Error::Io(err)) => assert_ne!(err.kind(), std::io::ErrorKind::InvalidInput),
+        other => panic!(
+            "unexpected result: {:?}",
+            other.map(|servers| servers.len())
+        ),
+    }
+}